@@ -1,6 +1,6 @@
 use std::f64::consts::PI;
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct UPoint {
     pub x: usize,
     pub y: usize,
@@ -14,7 +14,7 @@ impl UPoint {
         }
     }
 }
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct IPoint {
     pub x: i32,
     pub y: i32,
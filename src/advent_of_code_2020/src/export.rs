@@ -0,0 +1,67 @@
+use std::fs;
+
+use serde::Serialize;
+
+//Writes the records with the given closure and returns the CSV text
+pub fn to_csv<F>(write_records: F) -> Result<String, String>
+where
+    F: FnOnce(&mut csv::Writer<Vec<u8>>) -> csv::Result<()>,
+{
+    let mut writer = csv::Writer::from_writer(vec![]);
+    write_records(&mut writer).map_err(|e| format!("Failed to write the CSV: {}", e))?;
+    let output = writer
+        .into_inner()
+        .map_err(|e| format!("Failed to write the CSV: {}", e))?;
+    String::from_utf8(output).map_err(|e| format!("Failed to write the CSV: {}", e))
+}
+
+//One row per record with the field names as header
+pub fn serialize_csv<T: Serialize>(records: &[T]) -> Result<String, String> {
+    to_csv(|writer| {
+        for record in records {
+            writer.serialize(record)?;
+        }
+        Ok(())
+    })
+}
+
+//Writes the exported content to the file and reports where it went
+pub fn write_file(path: &str, content: Result<String, String>) {
+    match content.and_then(|c| fs::write(path, c).map_err(|e| e.to_string())) {
+        Ok(_) => println!("Written to {}", path),
+        Err(e) => println!("Failed to write {}: {}", path, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Record {
+        name: &'static str,
+        count: usize,
+    }
+
+    #[test]
+    fn test_csv() {
+        let records = [
+            Record {
+                name: "a",
+                count: 1,
+            },
+            Record {
+                name: "b, c",
+                count: 2,
+            },
+        ];
+        assert_eq!(
+            Ok("name,count\na,1\n\"b, c\",2\n".to_string()),
+            serialize_csv(&records)
+        );
+        assert_eq!(
+            Ok("x,y\n".to_string()),
+            to_csv(|writer| writer.write_record(["x", "y"]))
+        );
+    }
+}
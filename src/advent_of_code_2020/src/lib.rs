@@ -1,3 +1,5 @@
 pub mod simple_puzzles;
 pub mod coordination;
-pub mod load_input;
\ No newline at end of file
+pub mod export;
+pub mod load_input;
+pub mod pattern_cache;
\ No newline at end of file
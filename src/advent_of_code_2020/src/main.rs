@@ -3,6 +3,7 @@ use std::io;
 use advent_of_code_2020::simple_puzzles::{boarding_passes, declaration_forms, handheld_game_console, joltage_adapter, 
     luggage_rules, northpol_password_validation, number_processing, password_debug, toboggan_navigation, xmas_encryption, 
     waiting_room, ferry_navigation};
+use advent_of_code_2020::simple_puzzles::luggage_rules::DotScope;
 
 fn main() {
     println!("Select which function you want to call:");
@@ -30,6 +31,13 @@ fn main() {
     println!("22: Waiting Room Part 2");
     println!("23: Ferry Navigation Part 1");
     println!("24: Ferry Navigation Part 2");
+    println!("25: Luggage Rules Colours");
+    println!("26: Luggage Rules Lint");
+    println!("27: Navigation Route Drawing");
+    println!("28: Passport Export");
+    println!("29: Declaration Forms Export");
+    println!("30: Luggage Rules Graph");
+    println!("31: Luggage Rules Explain");
 
     let mut selection = String::new();
     io::stdin()
@@ -54,8 +62,8 @@ fn main() {
         10 => boarding_passes::part2(),
         11 => declaration_forms::part1(),
         12 => declaration_forms::part2(),
-        13 => luggage_rules::part1(),
-        14 => luggage_rules::part2(),
+        13 => luggage_rules::count_containers(&read_bag_color()),
+        14 => luggage_rules::count_contents(&read_bag_color()),
         15 => handheld_game_console::part1(),
         16 => handheld_game_console::part2(),
         17 => xmas_encryption::part1(),
@@ -66,6 +74,70 @@ fn main() {
         22 => waiting_room::part2(),
         23 => ferry_navigation::part1(),
         24 => ferry_navigation::part2(),
+        25 => luggage_rules::list_colors(),
+        26 => luggage_rules::lint_rules(),
+        27 => match read_slope() {
+            Some((right, down)) => toboggan_navigation::draw_route(right, down),
+            None => println!("Invalid slope!"),
+        },
+        28 => northpol_password_validation::export(),
+        29 => declaration_forms::export(),
+        30 => write_bag_graph(),
+        31 => explain_bags(),
         _ => println!("Invalid input!"),
     };
-}
\ No newline at end of file
+}
+
+fn read_bag_color() -> String {
+    println!("Which bag colour? (empty for {})", luggage_rules::DEFAULT_COLOR);
+    let mut color = String::new();
+    io::stdin()
+        .read_line(&mut color)
+        .expect("Failed to read input");
+
+    match color.trim() {
+        "" => luggage_rules::DEFAULT_COLOR.to_string(),
+        c => c.split_whitespace().collect::<Vec<_>>().join(" "),
+    }
+}
+
+fn read_slope() -> Option<(i32, usize)> {
+    println!("Which slope? Enter right and down, e.g. 3 1 (empty for 3 1)");
+    let mut slope = String::new();
+    io::stdin()
+        .read_line(&mut slope)
+        .expect("Failed to read input");
+
+    let steps = slope.split_whitespace().collect::<Vec<_>>();
+    match steps.as_slice() {
+        [] => Some((3, 1)),
+        [right, down] => Some((right.parse().ok()?, down.parse().ok()?)),
+        _ => None,
+    }
+}
+
+fn write_bag_graph() {
+    println!("Which bags? all, contents or containers (empty for all)");
+    let mut scope = String::new();
+    io::stdin()
+        .read_line(&mut scope)
+        .expect("Failed to read input");
+
+    match scope.trim() {
+        "" | "all" => luggage_rules::write_dot(DotScope::All),
+        "contents" => luggage_rules::write_dot(DotScope::ContentsOf(&read_bag_color())),
+        "containers" => luggage_rules::write_dot(DotScope::ContainersOf(&read_bag_color())),
+        _ => println!("Invalid scope!"),
+    }
+}
+
+fn explain_bags() {
+    println!("Which outer bag colour?");
+    let mut outer = String::new();
+    io::stdin()
+        .read_line(&mut outer)
+        .expect("Failed to read input");
+
+    let outer = outer.split_whitespace().collect::<Vec<_>>().join(" ");
+    luggage_rules::explain_containment(&outer, &read_bag_color());
+}
@@ -0,0 +1,44 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use regex::Regex;
+
+static CACHE: OnceLock<Mutex<HashMap<String, Arc<Regex>>>> = OnceLock::new();
+
+//Every regular expression is compiled through here, so a pattern that is used again, e.g. by
+//several schema fields, is only compiled once. The cache hands out shared owned handles.
+pub fn get(pattern: &str) -> Result<Arc<Regex>, regex::Error> {
+    let cache = CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+    let poisoned = "The pattern cache is poisoned";
+    if let Some(regex) = cache.lock().expect(poisoned).get(pattern) {
+        return Ok(Arc::clone(regex));
+    }
+    //Compiled outside of the lock, so other patterns can be looked up meanwhile
+    let regex = Arc::new(Regex::new(pattern)?);
+    let mut cache = cache.lock().expect(poisoned);
+    Ok(Arc::clone(
+        cache.entry(pattern.to_string()).or_insert(regex),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern_is_compiled_once() {
+        let first = get("^(\\d+)-(\\d+)$").unwrap();
+        let second = get("^(\\d+)-(\\d+)$").unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(first.is_match("1-3"));
+        assert!(!first.is_match("1-a"));
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        assert!(get("(\\d+").is_err());
+        assert!(get("(\\d+)").unwrap().is_match("42"));
+    }
+}
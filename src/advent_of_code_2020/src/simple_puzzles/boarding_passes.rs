@@ -1,91 +1,581 @@
+use std::{fmt, fs};
+
 use crate::load_input;
 
-#[derive(Debug)]
-struct BoardingPass {
-    row: u8,
-    column: u8,
+//The largest seat id has to fit into an u32
+const MAX_LAYOUT_BITS: u8 = 31;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SeatIdFormula {
+    //row * columns + column, this numbers all seats without gaps
+    RowMajor,
+    Linear { row_factor: u32, column_factor: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AircraftLayout {
+    row_bits: u8,
+    column_bits: u8,
+    //The first letter selects the lower half, the second one the upper half
+    row_letters: (char, char),
+    column_letters: (char, char),
+    seat_id_formula: SeatIdFormula,
+}
+
+impl Default for AircraftLayout {
+    fn default() -> AircraftLayout {
+        AircraftLayout {
+            row_bits: 7,
+            column_bits: 3,
+            row_letters: ('F', 'B'),
+            column_letters: ('L', 'R'),
+            seat_id_formula: SeatIdFormula::RowMajor,
+        }
+    }
+}
+
+impl AircraftLayout {
+    pub fn new(
+        row_bits: u8,
+        column_bits: u8,
+        row_letters: (char, char),
+        column_letters: (char, char),
+        seat_id_formula: SeatIdFormula,
+    ) -> Result<AircraftLayout, String> {
+        //Added as u32, the sum of two u8 can overflow
+        let total_bits = u32::from(row_bits) + u32::from(column_bits);
+        if row_bits == 0 || column_bits == 0 || total_bits > u32::from(MAX_LAYOUT_BITS) {
+            return Err(format!(
+                "The layout needs at least one row and column bit and at most {} bits in total",
+                MAX_LAYOUT_BITS
+            ));
+        }
+        if row_letters.0 == row_letters.1 || column_letters.0 == column_letters.1 {
+            return Err("The letters of the lower and upper half have to differ".to_string());
+        }
+        let layout = AircraftLayout {
+            row_bits,
+            column_bits,
+            row_letters,
+            column_letters,
+            seat_id_formula,
+        };
+        if let SeatIdFormula::Linear {
+            row_factor,
+            column_factor,
+        } = seat_id_formula
+        {
+            if row_factor == 0 || column_factor == 0 {
+                return Err("The factors of the seat id formula must not be 0".to_string());
+            }
+            if layout
+                .seat_id(layout.rows() - 1, layout.columns() - 1)
+                .is_none()
+            {
+                return Err("The seat ids of the layout do not fit into an u32".to_string());
+            }
+        }
+        Ok(layout)
+    }
+
+    pub fn rows(&self) -> u32 {
+        1 << self.row_bits
+    }
+
+    pub fn columns(&self) -> u32 {
+        1 << self.column_bits
+    }
+
+    pub fn pass_length(&self) -> usize {
+        (self.row_bits + self.column_bits) as usize
+    }
+
+    fn seat_id(&self, row: u32, column: u32) -> Option<u32> {
+        match self.seat_id_formula {
+            SeatIdFormula::RowMajor => Some(row * self.columns() + column),
+            SeatIdFormula::Linear {
+                row_factor,
+                column_factor,
+            } => row
+                .checked_mul(row_factor)?
+                .checked_add(column.checked_mul(column_factor)?),
+        }
+    }
+
+    //Inverts the seat id formula, None if no seat or more than one seat has this id
+    fn seat_of(&self, seat_id: u32) -> Option<(u32, u32)> {
+        match self.seat_id_formula {
+            SeatIdFormula::RowMajor => Some((seat_id / self.columns(), seat_id % self.columns()))
+                .filter(|(row, _)| *row < self.rows()),
+            SeatIdFormula::Linear {
+                row_factor,
+                column_factor,
+            } => {
+                //The rows solving row * row_factor + column * column_factor = seat_id are
+                //row_0 + k * step, only one of them may lie within the rows and columns
+                let (id, row_factor, column_factor) =
+                    (seat_id as i64, row_factor as i64, column_factor as i64);
+                let (divisor, inverse, _) = extended_gcd(row_factor, column_factor);
+                if id % divisor != 0 {
+                    return None;
+                }
+                let step = column_factor / divisor;
+                let row_0 = ((id / divisor) as i128 * inverse as i128).rem_euclid(step as i128);
+                let max_column = self.columns() as i64 - 1;
+                let lowest = (id - column_factor * max_column).max(0);
+                let lowest = (lowest + row_factor - 1) / row_factor;
+                let highest = (id / row_factor).min(self.rows() as i64 - 1);
+                let row = lowest + (row_0 as i64 - lowest).rem_euclid(step);
+                if row > highest || row + step <= highest {
+                    return None;
+                }
+                Some((row as u32, ((id - row * row_factor) / column_factor) as u32))
+            }
+        }
+    }
+}
+
+//Returns the greatest common divisor g and x, y with a * x + b * y = g
+fn extended_gcd(a: i64, b: i64) -> (i64, i64, i64) {
+    if b == 0 {
+        return (a, 1, 0);
+    }
+    let (divisor, x, y) = extended_gcd(b, a % b);
+    (divisor, y, x - (a / b) * y)
+}
+
+#[derive(Debug, PartialEq)]
+pub struct BoardingPass {
+    row: u32,
+    column: u32,
     seat_id: u32,
+    layout: AircraftLayout,
 }
 
-#[derive(Debug)]
-enum BoardingPassError {
-    InvalidSize(usize),
+#[derive(Debug, PartialEq)]
+pub enum BoardingPassError {
+    InvalidSize { expected: usize, found: usize },
     InvalidRowChar(char),
     InvalidColumnChar(char),
+    RowOutOfRange { row: u32, rows: u32 },
+    ColumnOutOfRange { column: u32, columns: u32 },
+    UnknownSeatId(u32),
+}
+
+impl fmt::Display for BoardingPassError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BoardingPassError::InvalidSize { expected, found } => {
+                write!(f, "Expected {} characters but found {}", expected, found)
+            }
+            BoardingPassError::InvalidRowChar(c) => write!(f, "Invalid row character {}", c),
+            BoardingPassError::InvalidColumnChar(c) => {
+                write!(f, "Invalid column character {}", c)
+            }
+            BoardingPassError::RowOutOfRange { row, rows } => {
+                write!(f, "The row {} is not within 0-{}", row, rows - 1)
+            }
+            BoardingPassError::ColumnOutOfRange { column, columns } => {
+                write!(f, "The column {} is not within 0-{}", column, columns - 1)
+            }
+            BoardingPassError::UnknownSeatId(seat_id) => {
+                write!(
+                    f,
+                    "The seat id {} does not belong to exactly one seat",
+                    seat_id
+                )
+            }
+        }
+    }
 }
 
 impl BoardingPass {
-    fn from(input: String) -> Result<BoardingPass, BoardingPassError> {
-        match input.chars().count() {
-            10 => {
-                let row = BoardingPass::determine_row(&input[..7])?;
-                let column = BoardingPass::determine_column(&input[7..])?;
-                let seat_id = (row as u32) * 8 + column as u32;
-
-                Ok(BoardingPass {
-                    row,
-                    column,
-                    seat_id,
-                })
+    pub fn from(input: String) -> Result<BoardingPass, BoardingPassError> {
+        BoardingPass::from_with_layout(&input, AircraftLayout::default())
+    }
+
+    pub fn from_with_layout(
+        input: &str,
+        layout: AircraftLayout,
+    ) -> Result<BoardingPass, BoardingPassError> {
+        PassDecoder::new(layout).decode(input)
+    }
+
+    pub fn from_seat(row: u32, column: u32) -> Result<BoardingPass, BoardingPassError> {
+        BoardingPass::from_seat_with_layout(row, column, AircraftLayout::default())
+    }
+
+    pub fn from_seat_with_layout(
+        row: u32,
+        column: u32,
+        layout: AircraftLayout,
+    ) -> Result<BoardingPass, BoardingPassError> {
+        if row >= layout.rows() {
+            return Err(BoardingPassError::RowOutOfRange {
+                row,
+                rows: layout.rows(),
+            });
+        }
+        if column >= layout.columns() {
+            return Err(BoardingPassError::ColumnOutOfRange {
+                column,
+                columns: layout.columns(),
+            });
+        }
+        Ok(BoardingPass {
+            row,
+            column,
+            //Overflows are ruled out when the layout is created
+            seat_id: layout.seat_id(row, column).unwrap(),
+            layout,
+        })
+    }
+
+    pub fn from_seat_id(seat_id: u32) -> Result<BoardingPass, BoardingPassError> {
+        BoardingPass::from_seat_id_with_layout(seat_id, AircraftLayout::default())
+    }
+
+    pub fn from_seat_id_with_layout(
+        seat_id: u32,
+        layout: AircraftLayout,
+    ) -> Result<BoardingPass, BoardingPassError> {
+        match layout.seat_of(seat_id) {
+            Some((row, column)) => BoardingPass::from_seat_with_layout(row, column, layout),
+            None => Err(BoardingPassError::UnknownSeatId(seat_id)),
+        }
+    }
+
+    pub fn row(&self) -> u32 {
+        self.row
+    }
+
+    pub fn column(&self) -> u32 {
+        self.column
+    }
+
+    pub fn seat_id(&self) -> u32 {
+        self.seat_id
+    }
+
+    //Highest bit first, the set bits take the upper half
+    fn encode(value: u32, bits: u8, (lower, upper): (char, char)) -> String {
+        (0..bits)
+            .rev()
+            .map(|bit| {
+                if value & (1 << bit) == 0 {
+                    lower
+                } else {
+                    upper
+                }
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for BoardingPass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            BoardingPass::encode(self.row, self.layout.row_bits, self.layout.row_letters),
+            BoardingPass::encode(
+                self.column,
+                self.layout.column_bits,
+                self.layout.column_letters
+            )
+        )
+    }
+}
+
+pub struct PassDecoder {
+    layout: AircraftLayout,
+    accept_lowercase: bool,
+}
+
+impl PassDecoder {
+    pub fn new(layout: AircraftLayout) -> PassDecoder {
+        PassDecoder {
+            layout,
+            accept_lowercase: false,
+        }
+    }
+
+    pub fn accept_lowercase(mut self, accept: bool) -> PassDecoder {
+        self.accept_lowercase = accept;
+        self
+    }
+
+    fn matches(&self, c: char, letter: char) -> bool {
+        c == letter || (self.accept_lowercase && c.eq_ignore_ascii_case(&letter))
+    }
+
+    //The pass is a binary number, the letters of the upper half are the set bits
+    fn decode_bits(&self, input: &str) -> Result<u32, BoardingPassError> {
+        let input = input.trim();
+        let found = input.chars().count();
+        if found != self.layout.pass_length() {
+            return Err(BoardingPassError::InvalidSize {
+                expected: self.layout.pass_length(),
+                found,
+            });
+        }
+        let mut bits = 0;
+        for (index, c) in input.chars().enumerate() {
+            let bit = if index < self.layout.row_bits as usize {
+                let (lower, upper) = self.layout.row_letters;
+                match c {
+                    c if self.matches(c, upper) => 1,
+                    c if self.matches(c, lower) => 0,
+                    c => return Err(BoardingPassError::InvalidRowChar(c)),
+                }
+            } else {
+                let (lower, upper) = self.layout.column_letters;
+                match c {
+                    c if self.matches(c, upper) => 1,
+                    c if self.matches(c, lower) => 0,
+                    c => return Err(BoardingPassError::InvalidColumnChar(c)),
+                }
+            };
+            bits = bits << 1 | bit;
+        }
+        Ok(bits)
+    }
+
+    pub fn decode(&self, input: &str) -> Result<BoardingPass, BoardingPassError> {
+        let bits = self.decode_bits(input)?;
+        let row = bits >> self.layout.column_bits;
+        let column = bits & (self.layout.columns() - 1);
+        BoardingPass::from_seat_with_layout(row, column, self.layout)
+    }
+
+    //Decodes one pass per line, empty lines are skipped
+    pub fn decode_seat_ids(&self, input: &str) -> Result<SeatIds, String> {
+        let mut seat_ids = SeatIds {
+            ids: vec![],
+            min: None,
+            max: None,
+        };
+        for (number, line) in input.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
             }
-            s => Err(BoardingPassError::InvalidSize(s)),
+            let seat_id = match self.decode(line) {
+                Ok(pass) => pass.seat_id,
+                Err(e) => return Err(format!("Line {}: {}", number + 1, e)),
+            };
+            seat_ids.min = Some(seat_ids.min.map_or(seat_id, |min: u32| min.min(seat_id)));
+            seat_ids.max = Some(seat_ids.max.map_or(seat_id, |max: u32| max.max(seat_id)));
+            seat_ids.ids.push(seat_id);
         }
+        Ok(seat_ids)
+    }
+}
+
+pub struct SeatIds {
+    pub ids: Vec<u32>,
+    pub min: Option<u32>,
+    pub max: Option<u32>,
+}
+
+impl SeatIds {
+    fn occupied(&self, min: u32, max: u32) -> Vec<bool> {
+        let mut occupied = vec![false; (max - min + 1) as usize];
+        for id in self.ids.iter() {
+            occupied[(id - min) as usize] = true;
+        }
+        occupied
+    }
+
+    //Unused seat ids between the smallest and the largest one, without sorting
+    pub fn missing(&self) -> Vec<u32> {
+        let (min, max) = match (self.min, self.max) {
+            (Some(min), Some(max)) => (min, max),
+            _ => return vec![],
+        };
+        let occupied = self.occupied(min, max);
+        (min..=max)
+            .filter(|id| !occupied[(id - min) as usize])
+            .collect()
+    }
+
+    //Missing seat ids whose neighbours are both in use
+    pub fn open_seats(&self) -> Vec<u32> {
+        let (min, max) = match (self.min, self.max) {
+            (Some(min), Some(max)) if max - min >= 2 => (min, max),
+            _ => return vec![],
+        };
+        let occupied = self.occupied(min, max);
+        (1..occupied.len() - 1)
+            .filter(|i| !occupied[*i] && occupied[i - 1] && occupied[i + 1])
+            .map(|i| min + i as u32)
+            .collect()
+    }
+}
+
+//A run of empty seats in row major order, it may span several rows
+#[derive(Debug, PartialEq)]
+pub struct EmptyBlock {
+    pub first: (u32, u32),
+    pub last: (u32, u32),
+    pub seats: usize,
+    //The block starts at the first or ends at the last seat of the cabin
+    pub at_front: bool,
+    pub at_back: bool,
+}
+
+impl EmptyBlock {
+    pub fn is_enclosed(&self) -> bool {
+        !self.at_front && !self.at_back
+    }
+}
+
+pub struct SeatMap {
+    layout: AircraftLayout,
+    //Number of passes per seat in row major order
+    passes: Vec<usize>,
+}
+
+impl SeatMap {
+    pub fn from(passes: &[BoardingPass]) -> Result<SeatMap, String> {
+        let layout = passes
+            .first()
+            .map_or(AircraftLayout::default(), |p| p.layout);
+        SeatMap::from_with_layout(passes, layout)
     }
 
-    fn determine_row(input: &str) -> Result<u8, BoardingPassError> {
-        let mut row: u8 = 0;
-        let mut to_add: u8 = 64;
-        for c in input.chars() {
-            match c {
-                'F' => (),
-                'B' => row += to_add,
-                a => return Err(BoardingPassError::InvalidRowChar(a)),
+    pub fn from_with_layout(
+        passes: &[BoardingPass],
+        layout: AircraftLayout,
+    ) -> Result<SeatMap, String> {
+        let mut seat_map = SeatMap {
+            layout,
+            passes: vec![0; (layout.rows() * layout.columns()) as usize],
+        };
+        for pass in passes {
+            if pass.layout != layout {
+                return Err(format!(
+                    "The boarding pass {} belongs to a different aircraft layout",
+                    pass
+                ));
             }
-            to_add = to_add / 2;
+            let index = seat_map.index(pass.row, pass.column);
+            seat_map.passes[index] += 1;
         }
+        Ok(seat_map)
+    }
+
+    fn index(&self, row: u32, column: u32) -> usize {
+        (row * self.layout.columns() + column) as usize
+    }
+
+    fn seat(&self, index: usize) -> (u32, u32) {
+        let index = index as u32;
+        (index / self.layout.columns(), index % self.layout.columns())
+    }
+
+    pub fn passes_for(&self, row: u32, column: u32) -> usize {
+        if row >= self.layout.rows() || column >= self.layout.columns() {
+            return 0;
+        }
+        self.passes[self.index(row, column)]
+    }
+
+    //Seats with more than one boarding pass and the number of their passes
+    pub fn duplicates(&self) -> Vec<((u32, u32), usize)> {
+        self.passes
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 1)
+            .map(|(index, count)| (self.seat(index), *count))
+            .collect()
+    }
 
-        Ok(row)
+    pub fn empty_seats(&self) -> Vec<(u32, u32)> {
+        self.passes
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count == 0)
+            .map(|(index, _)| self.seat(index))
+            .collect()
     }
 
-    fn determine_column(input: &str) -> Result<u8, BoardingPassError> {
-        let mut column: u8 = 0;
-        let mut to_add: u8 = 4;
-        for c in input.chars() {
-            match c {
-                'L' => (),
-                'R' => column += to_add,
-                a => return Err(BoardingPassError::InvalidColumnChar(a)),
+    pub fn empty_blocks(&self) -> Vec<EmptyBlock> {
+        let mut blocks = vec![];
+        let mut start = None;
+        for index in 0..=self.passes.len() {
+            let is_empty = index < self.passes.len() && self.passes[index] == 0;
+            match (start, is_empty) {
+                (None, true) => start = Some(index),
+                (Some(first), false) => {
+                    blocks.push(EmptyBlock {
+                        first: self.seat(first),
+                        last: self.seat(index - 1),
+                        seats: index - first,
+                        at_front: first == 0,
+                        at_back: index == self.passes.len(),
+                    });
+                    start = None;
+                }
+                _ => (),
             }
-            to_add = to_add / 2;
         }
-        Ok(column)
+        blocks
+    }
+
+    //One line per row, # is an occupied seat, ! a seat with duplicate passes and . an empty one
+    pub fn render(&self) -> String {
+        let width = (self.layout.rows() - 1).to_string().len();
+        let mut output = String::new();
+        for row in 0..self.layout.rows() {
+            let seats = (0..self.layout.columns())
+                .map(|column| match self.passes_for(row, column) {
+                    0 => '.',
+                    1 => '#',
+                    _ => '!',
+                })
+                .collect::<String>();
+            output.push_str(&format!("{:>width$} {}\n", row, seats, width = width));
+        }
+        output
     }
 }
 
 pub fn part1() {
-    let board_passes = load_boarding_passes();
+    let input = fs::read_to_string("./resources/Day5Input.txt").expect("Failed to read the input");
+    let seat_ids = PassDecoder::new(AircraftLayout::default())
+        .decode_seat_ids(&input)
+        .unwrap_or_else(|e| panic!("Failed to parse a boarding pass: {}", e));
 
-    match board_passes.iter().max_by_key(|bp| bp.seat_id) {
-        Some(bp) => println!("The maximum seat id is {}", bp.seat_id),
-        None => println!("None boaringd pass found with a maximum id"),
+    match seat_ids.max.map(BoardingPass::from_seat_id) {
+        Some(Ok(bp)) => println!(
+            "The maximum seat id is {} (row {}, column {})",
+            bp.seat_id, bp.row, bp.column
+        ),
+        _ => println!("None boaringd pass found with a maximum id"),
     };
 }
 
 pub fn part2() {
-    let mut board_passes = load_boarding_passes();
-    board_passes.sort_by_key(|p| p.seat_id);
-
-    let mut last_used_seat = None;
-    for bp in board_passes {
-        match last_used_seat {
-            None => (),
-            Some(id) => {
-                if bp.seat_id == id + 2 {
-                    println!("An open seat found at {}", bp.seat_id - 1);
-                }
-            }
+    let board_passes = load_boarding_passes();
+    let seat_map = SeatMap::from(&board_passes).expect("All passes use the same layout");
+
+    for ((row, column), count) in seat_map.duplicates() {
+        println!(
+            "The seat in row {} column {} has {} passes",
+            row, column, count
+        );
+    }
+    //Our seat is the only empty one with occupied seats on both sides
+    for block in seat_map.empty_blocks() {
+        if block.seats == 1 && block.is_enclosed() {
+            let (row, column) = block.first;
+            let open_seat =
+                BoardingPass::from_seat(row, column).expect("The seat map only contains seats");
+            println!(
+                "An open seat found at {} (boarding pass {})",
+                open_seat.seat_id, open_seat
+            );
         }
-        last_used_seat = Some(bp.seat_id);
     }
 }
 
@@ -95,7 +585,10 @@ fn load_boarding_passes() -> Vec<BoardingPass> {
 
     input_file
         .into_iter()
-        .map(|l| BoardingPass::from(l).expect("Failed to parse a boarding pass"))
+        .map(|l| {
+            BoardingPass::from(l)
+                .unwrap_or_else(|e| panic!("Failed to parse a boarding pass: {}", e))
+        })
         .collect::<Vec<_>>()
 }
 
@@ -117,4 +610,356 @@ pub mod tests {
         assert_eq!(119, pass_2.seat_id);
         assert_eq!(820, pass_3.seat_id);
     }
+
+    #[test]
+    fn test_encode_boarding_pass() {
+        let pass = BoardingPass::from_seat(70, 7).expect("Failed to encode the seat");
+        assert_eq!(567, pass.seat_id);
+        assert_eq!("BFFFBBFRRR", pass.to_string());
+        assert_eq!(
+            "BBFFBBFRLL",
+            BoardingPass::from_seat_id(820).unwrap().to_string()
+        );
+        assert_eq!(
+            "FFFFFFFLLL",
+            BoardingPass::from_seat_id(0).unwrap().to_string()
+        );
+        assert_eq!(
+            "BBBBBBBRRR",
+            BoardingPass::from_seat(127, 7).unwrap().to_string()
+        );
+
+        assert_eq!(
+            Err(BoardingPassError::RowOutOfRange {
+                row: 128,
+                rows: 128
+            }),
+            BoardingPass::from_seat(128, 0)
+        );
+        assert_eq!(
+            Err(BoardingPassError::ColumnOutOfRange {
+                column: 8,
+                columns: 8
+            }),
+            BoardingPass::from_seat(0, 8)
+        );
+        assert_eq!(
+            Err(BoardingPassError::UnknownSeatId(1024)),
+            BoardingPass::from_seat_id(1024)
+        );
+        assert_eq!(
+            Err(BoardingPassError::InvalidRowChar('É')),
+            BoardingPass::from("ÉFFFBBFRRR".to_string())
+        );
+        assert_eq!(
+            Err(BoardingPassError::InvalidColumnChar('É')),
+            BoardingPass::from("FFFFFFFRRÉ".to_string())
+        );
+    }
+
+    #[test]
+    fn test_round_trip_all_seats() {
+        for seat_id in 0..1024 {
+            let pass = BoardingPass::from_seat_id(seat_id).expect("Failed to encode the seat");
+            assert_eq!(seat_id, pass.row() * 8 + pass.column());
+            let decoded = BoardingPass::from(pass.to_string()).expect("Failed to decode the pass");
+            assert_eq!(pass, decoded);
+            assert_eq!(
+                pass,
+                BoardingPass::from_seat(pass.row(), pass.column()).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_wide_body_layout() {
+        let layout =
+            AircraftLayout::new(8, 4, ('F', 'B'), ('L', 'R'), SeatIdFormula::RowMajor).unwrap();
+        assert_eq!(12, layout.pass_length());
+        let pass = BoardingPass::from_with_layout("BFFFFFFBRLLR", layout).unwrap();
+        assert_eq!(129, pass.row());
+        assert_eq!(9, pass.column());
+        assert_eq!(129 * 16 + 9, pass.seat_id());
+        assert_eq!("BFFFFFFBRLLR", pass.to_string());
+        assert_eq!(
+            Err(BoardingPassError::InvalidSize {
+                expected: 12,
+                found: 10
+            }),
+            BoardingPass::from_with_layout("BFFFBBFRRR", layout)
+        );
+        for seat_id in (0..layout.rows() * layout.columns()).step_by(7) {
+            let pass = BoardingPass::from_seat_id_with_layout(seat_id, layout).unwrap();
+            assert_eq!(
+                pass,
+                BoardingPass::from_with_layout(&pass.to_string(), layout).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_custom_alphabet_and_formula() {
+        let layout = AircraftLayout::new(
+            5,
+            3,
+            ('0', '1'),
+            ('a', 'b'),
+            SeatIdFormula::Linear {
+                row_factor: 10,
+                column_factor: 1,
+            },
+        )
+        .unwrap();
+        let pass = BoardingPass::from_with_layout("10011bab", layout).unwrap();
+        assert_eq!((19, 5), (pass.row(), pass.column()));
+        assert_eq!(195, pass.seat_id());
+        assert_eq!(
+            pass,
+            BoardingPass::from_seat_id_with_layout(195, layout).unwrap()
+        );
+        assert_eq!(
+            Err(BoardingPassError::UnknownSeatId(198)),
+            BoardingPass::from_seat_id_with_layout(198, layout)
+        );
+        assert_eq!(
+            Err(BoardingPassError::InvalidRowChar('F')),
+            BoardingPass::from_with_layout("F0011bab", layout)
+        );
+
+        //Every seat id below 8 belongs to several seats
+        let ambiguous = AircraftLayout::new(
+            2,
+            3,
+            ('F', 'B'),
+            ('L', 'R'),
+            SeatIdFormula::Linear {
+                row_factor: 1,
+                column_factor: 1,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            Err(BoardingPassError::UnknownSeatId(3)),
+            BoardingPass::from_seat_id_with_layout(3, ambiguous)
+        );
+        assert_eq!(
+            10,
+            BoardingPass::from_seat_id_with_layout(10, ambiguous)
+                .unwrap()
+                .seat_id()
+        );
+    }
+
+    #[test]
+    fn test_linear_seat_ids() {
+        let factors = [
+            (1, 1),
+            (2, 3),
+            (3, 2),
+            (4, 6),
+            (8, 1),
+            (10, 1),
+            (1, 4),
+            (5, 7),
+        ];
+        for (row_factor, column_factor) in factors {
+            let formula = SeatIdFormula::Linear {
+                row_factor,
+                column_factor,
+            };
+            let layout = AircraftLayout::new(3, 2, ('F', 'B'), ('L', 'R'), formula).unwrap();
+            let max_id = layout
+                .seat_id(layout.rows() - 1, layout.columns() - 1)
+                .unwrap();
+            for seat_id in 0..=max_id + 2 {
+                let seats = (0..layout.rows())
+                    .flat_map(|row| (0..layout.columns()).map(move |column| (row, column)))
+                    .filter(|(row, column)| layout.seat_id(*row, *column) == Some(seat_id))
+                    .collect::<Vec<_>>();
+                let expected = match seats.as_slice() {
+                    [seat] => Some(*seat),
+                    _ => None,
+                };
+                assert_eq!(
+                    expected,
+                    layout.seat_of(seat_id),
+                    "{:?} {}",
+                    formula,
+                    seat_id
+                );
+            }
+        }
+
+        //Wide enough that scanning the rows would take a while
+        let formula = SeatIdFormula::Linear {
+            row_factor: 3,
+            column_factor: 1 << 30,
+        };
+        let layout = AircraftLayout::new(30, 1, ('F', 'B'), ('L', 'R'), formula).unwrap();
+        let seat_id = layout.seat_id(1 << 29, 1).unwrap();
+        assert_eq!(Some((1 << 29, 1)), layout.seat_of(seat_id));
+    }
+
+    #[test]
+    fn test_invalid_layout() {
+        let formula = SeatIdFormula::RowMajor;
+        assert!(AircraftLayout::new(0, 3, ('F', 'B'), ('L', 'R'), formula).is_err());
+        assert!(AircraftLayout::new(30, 3, ('F', 'B'), ('L', 'R'), formula).is_err());
+        assert!(AircraftLayout::new(200, 200, ('F', 'B'), ('L', 'R'), formula).is_err());
+        assert!(AircraftLayout::new(128, 128, ('F', 'B'), ('L', 'R'), formula).is_err());
+        assert!(AircraftLayout::new(7, 3, ('F', 'F'), ('L', 'R'), formula).is_err());
+        let overflowing = SeatIdFormula::Linear {
+            row_factor: u32::MAX,
+            column_factor: 1,
+        };
+        assert!(AircraftLayout::new(7, 3, ('F', 'B'), ('L', 'R'), overflowing).is_err());
+        assert_eq!(
+            Ok(AircraftLayout::default()),
+            AircraftLayout::new(7, 3, ('F', 'B'), ('L', 'R'), formula)
+        );
+    }
+
+    #[test]
+    fn test_seat_map() {
+        let layout =
+            AircraftLayout::new(2, 2, ('F', 'B'), ('L', 'R'), SeatIdFormula::RowMajor).unwrap();
+        let passes = ["FFRL", "FFRR", "FBLL", "FBRR", "FBRR", "BFRL", "BBLL"]
+            .iter()
+            .map(|p| BoardingPass::from_with_layout(p, layout).unwrap())
+            .collect::<Vec<_>>();
+        let seat_map = SeatMap::from(&passes).unwrap();
+
+        assert_eq!(vec![((1, 3), 2)], seat_map.duplicates());
+        assert_eq!(2, seat_map.passes_for(1, 3));
+        assert_eq!(0, seat_map.passes_for(4, 0));
+        assert_eq!(
+            vec![
+                (0, 0),
+                (0, 1),
+                (1, 1),
+                (1, 2),
+                (2, 0),
+                (2, 1),
+                (2, 3),
+                (3, 1),
+                (3, 2),
+                (3, 3)
+            ],
+            seat_map.empty_seats()
+        );
+        assert_eq!(
+            vec![
+                EmptyBlock {
+                    first: (0, 0),
+                    last: (0, 1),
+                    seats: 2,
+                    at_front: true,
+                    at_back: false
+                },
+                EmptyBlock {
+                    first: (1, 1),
+                    last: (1, 2),
+                    seats: 2,
+                    at_front: false,
+                    at_back: false
+                },
+                EmptyBlock {
+                    first: (2, 0),
+                    last: (2, 1),
+                    seats: 2,
+                    at_front: false,
+                    at_back: false
+                },
+                EmptyBlock {
+                    first: (2, 3),
+                    last: (2, 3),
+                    seats: 1,
+                    at_front: false,
+                    at_back: false
+                },
+                EmptyBlock {
+                    first: (3, 1),
+                    last: (3, 3),
+                    seats: 3,
+                    at_front: false,
+                    at_back: true
+                },
+            ],
+            seat_map.empty_blocks()
+        );
+        assert_eq!("0 ..##\n1 #..!\n2 ..#.\n3 #...\n", seat_map.render());
+
+        let other_layout = SeatMap::from_with_layout(&passes, AircraftLayout::default());
+        assert!(other_layout.is_err());
+        let empty = SeatMap::from(&[]).unwrap().empty_blocks();
+        assert_eq!(1, empty.len());
+        assert_eq!(1024, empty[0].seats);
+        assert!(empty[0].at_front && empty[0].at_back);
+    }
+
+    #[test]
+    fn test_pass_decoder() {
+        let decoder = PassDecoder::new(AircraftLayout::default());
+        assert_eq!(567, decoder.decode(" BFFFBBFRRR\r\n").unwrap().seat_id());
+        assert_eq!(
+            Err(BoardingPassError::InvalidRowChar('b')),
+            decoder.decode("bFFFBBFRRR")
+        );
+        assert_eq!(
+            Err(BoardingPassError::InvalidRowChar(' ')),
+            decoder.decode("BFF BBFRRR")
+        );
+        assert_eq!(
+            Err(BoardingPassError::InvalidSize {
+                expected: 10,
+                found: 9
+            }),
+            decoder.decode("BFFFBBFRR")
+        );
+
+        let decoder = decoder.accept_lowercase(true);
+        assert_eq!(567, decoder.decode("bfffbbfrrr").unwrap().seat_id());
+        assert_eq!(820, decoder.decode("BbFFbBFRlL").unwrap().seat_id());
+        assert_eq!(
+            Err(BoardingPassError::InvalidColumnChar('x')),
+            decoder.decode("BFFFBBFRRx")
+        );
+    }
+
+    #[test]
+    fn test_decode_seat_ids() {
+        let decoder = PassDecoder::new(AircraftLayout::default());
+        let seat_ids = decoder
+            .decode_seat_ids("BFFFBBFRRR\n\nBFFFBBFRRL\n  BFFFBBFRLL\nBFFFBBFRLR\nBFFFBBFLLR\n")
+            .unwrap();
+        assert_eq!(vec![567, 566, 564, 565, 561], seat_ids.ids);
+        assert_eq!((Some(561), Some(567)), (seat_ids.min, seat_ids.max));
+        assert_eq!(vec![562, 563], seat_ids.missing());
+        assert!(seat_ids.open_seats().is_empty());
+
+        let seat_ids = decoder.decode_seat_ids("FFFFFFFLLR\nFFFFFFFLRR").unwrap();
+        assert_eq!(vec![2], seat_ids.open_seats());
+        assert_eq!(
+            Err("Line 2: Invalid column character X".to_string()),
+            decoder
+                .decode_seat_ids("FFFFFFFLLR\nFFFFFFFLRX")
+                .map(|s| s.ids)
+        );
+        assert!(decoder.decode_seat_ids("").unwrap().missing().is_empty());
+
+        let input = fs::read_to_string("./resources/Day5Input.txt").unwrap();
+        let seat_ids = decoder.decode_seat_ids(&input).unwrap();
+        assert_eq!(Some(822), seat_ids.max);
+        assert_eq!(vec![705], seat_ids.open_seats());
+    }
+
+    #[test]
+    fn test_input_passes_are_canonical() {
+        let input_file = load_input::load_strings("./resources/Day5Input.txt")
+            .expect("Failed to read the input");
+        for line in input_file {
+            let pass = BoardingPass::from(line.to_string()).expect("Failed to parse the pass");
+            assert_eq!(line, pass.to_string());
+        }
+    }
 }
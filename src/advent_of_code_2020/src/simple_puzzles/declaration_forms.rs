@@ -1,103 +1,424 @@
-use std::collections::HashSet;
+use std::fmt;
 
-use crate::load_input;
+use serde::Serialize;
 
-struct DeclarationFormGrouping {
+use crate::{export, load_input};
+
+const MAX_ALPHABET_SIZE: usize = 64;
+
+//The questions of the questionnaire, every letter is one bit of an answer set
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alphabet {
+    letters: Vec<char>,
+}
+
+impl Default for Alphabet {
+    fn default() -> Alphabet {
+        Alphabet {
+            letters: ('a'..='z').collect(),
+        }
+    }
+}
+
+impl Alphabet {
+    pub fn new(letters: &str) -> Result<Alphabet, String> {
+        let letters = letters.chars().collect::<Vec<_>>();
+        if letters.is_empty() || letters.len() > MAX_ALPHABET_SIZE {
+            return Err(format!(
+                "An alphabet needs between 1 and {} letters but has {}",
+                MAX_ALPHABET_SIZE,
+                letters.len()
+            ));
+        }
+        for (i, letter) in letters.iter().enumerate() {
+            if letters[..i].contains(letter) {
+                return Err(format!(
+                    "The letter {} is part of the alphabet twice",
+                    letter
+                ));
+            }
+        }
+        Ok(Alphabet { letters })
+    }
+
+    pub fn len(&self) -> usize {
+        self.letters.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.letters.is_empty()
+    }
+
+    fn index_of(&self, letter: char) -> Option<usize> {
+        self.letters.iter().position(|l| *l == letter)
+    }
+
+    pub fn full_set(&self) -> AnswerSet {
+        match self.letters.len() {
+            MAX_ALPHABET_SIZE => AnswerSet(u64::MAX),
+            len => AnswerSet((1 << len) - 1),
+        }
+    }
+
+    //The letters of the set in alphabet order
+    pub fn letters_of(&self, set: AnswerSet) -> Vec<char> {
+        self.letters
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| set.contains(*i))
+            .map(|(_, l)| *l)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AnswerSet(u64);
+
+impl AnswerSet {
+    pub fn union(self, other: AnswerSet) -> AnswerSet {
+        AnswerSet(self.0 | other.0)
+    }
+
+    pub fn intersection(self, other: AnswerSet) -> AnswerSet {
+        AnswerSet(self.0 & other.0)
+    }
+
+    pub fn count(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn contains(self, index: usize) -> bool {
+        index < MAX_ALPHABET_SIZE && self.0 & (1 << index) != 0
+    }
+
+    fn insert(&mut self, index: usize) {
+        self.0 |= 1 << index;
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum DeclarationError {
+    //Line and column are 1-based and relative to the group
+    UnknownAnswer {
+        answer: char,
+        line: usize,
+        column: usize,
+    },
+}
+
+impl fmt::Display for DeclarationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeclarationError::UnknownAnswer {
+                answer,
+                line,
+                column,
+            } => write!(
+                f,
+                "The answer {} in line {} column {} is not part of the questionnaire",
+                answer, line, column
+            ),
+        }
+    }
+}
+
+pub struct DeclarationFormGrouping {
     forms: Vec<DeclarationForm>,
+    alphabet: Alphabet,
 }
 
 impl DeclarationFormGrouping {
-    fn from(input: Vec<String>) -> DeclarationFormGrouping {
-        DeclarationFormGrouping {
-            forms: input
-                .iter()
-                .map(|line| DeclarationForm::from(line))
-                .collect::<Vec<_>>(),
+    pub fn from(input: Vec<String>) -> Result<DeclarationFormGrouping, DeclarationError> {
+        DeclarationFormGrouping::from_with_alphabet(input, &Alphabet::default())
+    }
+
+    pub fn from_with_alphabet(
+        input: Vec<String>,
+        alphabet: &Alphabet,
+    ) -> Result<DeclarationFormGrouping, DeclarationError> {
+        let mut forms = vec![];
+        for (i, line) in input.iter().enumerate() {
+            match DeclarationForm::from(line, alphabet) {
+                Ok(form) => forms.push(form),
+                Err((answer, column)) => {
+                    return Err(DeclarationError::UnknownAnswer {
+                        answer,
+                        line: i + 1,
+                        column,
+                    })
+                }
+            }
         }
+        Ok(DeclarationFormGrouping {
+            forms,
+            alphabet: alphabet.clone(),
+        })
+    }
+
+    pub fn alphabet(&self) -> &Alphabet {
+        &self.alphabet
+    }
+
+    //Number of people in the group
+    pub fn len(&self) -> usize {
+        self.forms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.forms.is_empty()
     }
 
-    fn get_anyones_yes_answered_questions(&self) -> HashSet<char> {
+    pub fn get_anyones_yes_answered_questions(&self) -> AnswerSet {
         self.forms
             .iter()
-            .flat_map(|f| f.answered_yes.iter().cloned())
-            .collect::<HashSet<_>>()
+            .fold(AnswerSet::default(), |set, f| set.union(f.answered_yes))
+    }
+
+    pub fn get_everyones_yes_answered_questions(&self) -> AnswerSet {
+        if self.forms.is_empty() {
+            return AnswerSet::default();
+        }
+        self.forms.iter().fold(self.alphabet.full_set(), |set, f| {
+            set.intersection(f.answered_yes)
+        })
     }
 
-    fn get_everyones_yes_answered_questions(&self) -> HashSet<char> {
-        let yes_answers = self
-            .forms
+    //Number of people answering yes per question, in alphabet order
+    pub fn question_counts(&self) -> Vec<usize> {
+        (0..self.alphabet.len())
+            .map(|i| {
+                self.forms
+                    .iter()
+                    .filter(|f| f.answered_yes.contains(i))
+                    .count()
+            })
+            .collect()
+    }
+
+    pub fn answered_by_at_least(&self, people: usize) -> AnswerSet {
+        let mut set = AnswerSet::default();
+        for (i, count) in self.question_counts().into_iter().enumerate() {
+            if count >= people {
+                set.insert(i);
+            }
+        }
+        set
+    }
+
+    //Questions answered yes by at least the given percentage of the group
+    pub fn answered_by_at_least_percent(&self, percent: u32) -> AnswerSet {
+        if self.forms.is_empty() {
+            return AnswerSet::default();
+        }
+        let people = (percent as usize * self.forms.len()).div_ceil(100);
+        self.answered_by_at_least(people)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QuestionFrequency {
+    pub question: char,
+    //People answering yes and groups in which anyone or everyone answered yes
+    pub people: usize,
+    pub groups_anyone: usize,
+    pub groups_everyone: usize,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SurveyStatistics {
+    pub groups: usize,
+    pub people: usize,
+    pub questions: Vec<QuestionFrequency>,
+}
+
+impl SurveyStatistics {
+    pub fn from(groups: &[DeclarationFormGrouping]) -> Result<SurveyStatistics, String> {
+        let alphabet = groups
+            .first()
+            .map_or(Alphabet::default(), |g| g.alphabet.clone());
+        if groups.iter().any(|g| g.alphabet != alphabet) {
+            return Err("All groups have to use the same questionnaire alphabet".to_string());
+        }
+        let mut questions = alphabet
+            .letters
             .iter()
-            .map(|f| f.answered_yes.iter().cloned().collect::<Vec<_>>())
+            .map(|question| QuestionFrequency {
+                question: *question,
+                people: 0,
+                groups_anyone: 0,
+                groups_everyone: 0,
+            })
             .collect::<Vec<_>>();
+        for group in groups {
+            let anyone = group.get_anyones_yes_answered_questions();
+            let everyone = group.get_everyones_yes_answered_questions();
+            for (i, count) in group.question_counts().into_iter().enumerate() {
+                questions[i].people += count;
+                questions[i].groups_anyone += anyone.contains(i) as usize;
+                questions[i].groups_everyone += everyone.contains(i) as usize;
+            }
+        }
+        Ok(SurveyStatistics {
+            groups: groups.len(),
+            people: groups.iter().map(|g| g.len()).sum(),
+            questions,
+        })
+    }
 
-        if yes_answers.iter().len() > 0 {
-            let start: HashSet<char> = yes_answers
-                .get(0)
-                .unwrap()
-                .into_iter()
-                .cloned()
-                .collect::<HashSet<_>>();
-            yes_answers.into_iter().fold(start, |folded, next| {
-                let next_set = next.into_iter().collect::<HashSet<_>>();
-                let result = folded.intersection(&next_set).cloned().collect();
-                result
-            })
-        } else {
-            HashSet::new()
+    //All questions sharing the highest number of yes answers
+    pub fn most_common(&self) -> Vec<&QuestionFrequency> {
+        match self.questions.iter().map(|q| q.people).max() {
+            Some(max) if max > 0 => self.questions.iter().filter(|q| q.people == max).collect(),
+            _ => vec![],
+        }
+    }
+
+    pub fn least_common(&self) -> Vec<&QuestionFrequency> {
+        match self.questions.iter().map(|q| q.people).min() {
+            Some(min) => self.questions.iter().filter(|q| q.people == min).collect(),
+            None => vec![],
         }
     }
 }
 
+#[derive(Debug, PartialEq, Serialize)]
+pub struct GroupReport {
+    //1-based position of the group in the input
+    pub group: usize,
+    pub size: usize,
+    pub anyone: String,
+    pub anyone_count: usize,
+    pub everyone: String,
+    pub everyone_count: usize,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SurveyReport {
+    pub groups: Vec<GroupReport>,
+    pub summary: SurveyStatistics,
+}
+
+impl SurveyReport {
+    pub fn from(groups: &[DeclarationFormGrouping]) -> Result<SurveyReport, String> {
+        let reports = groups
+            .iter()
+            .enumerate()
+            .map(|(i, g)| {
+                let anyone = g.get_anyones_yes_answered_questions();
+                let everyone = g.get_everyones_yes_answered_questions();
+                GroupReport {
+                    group: i + 1,
+                    size: g.len(),
+                    anyone: g.alphabet.letters_of(anyone).into_iter().collect(),
+                    anyone_count: anyone.count(),
+                    everyone: g.alphabet.letters_of(everyone).into_iter().collect(),
+                    everyone_count: everyone.count(),
+                }
+            })
+            .collect();
+        Ok(SurveyReport {
+            groups: reports,
+            summary: SurveyStatistics::from(groups)?,
+        })
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to export the report: {}", e))
+    }
+
+    //One row per group
+    pub fn groups_to_csv(&self) -> Result<String, String> {
+        export::serialize_csv(&self.groups)
+    }
+
+    //One row per question with its popularity over all groups
+    pub fn questions_to_csv(&self) -> Result<String, String> {
+        export::serialize_csv(&self.summary.questions)
+    }
+}
+
+impl fmt::Display for SurveyReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let questions = |questions: Vec<&QuestionFrequency>| {
+            questions
+                .iter()
+                .map(|q| q.question.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        writeln!(
+            f,
+            "{} people in {} groups answered the survey",
+            self.summary.people, self.summary.groups
+        )?;
+        writeln!(
+            f,
+            "The most common questions are {}",
+            questions(self.summary.most_common())
+        )?;
+        writeln!(
+            f,
+            "The least common questions are {}",
+            questions(self.summary.least_common())
+        )
+    }
+}
+
 struct DeclarationForm {
-    answered_yes: Vec<char>,
+    answered_yes: AnswerSet,
 }
 
 impl DeclarationForm {
-    fn from(input: &String) -> DeclarationForm {
-        DeclarationForm {
-            answered_yes: input.chars().collect::<Vec<_>>(),
+    //Fails with the unknown answer and its 1-based column
+    fn from(input: &str, alphabet: &Alphabet) -> Result<DeclarationForm, (char, usize)> {
+        let mut answered_yes = AnswerSet::default();
+        for (i, c) in input.trim_end().chars().enumerate() {
+            match alphabet.index_of(c) {
+                Some(index) => answered_yes.insert(index),
+                None => return Err((c, i + 1)),
+            }
         }
+        Ok(DeclarationForm { answered_yes })
     }
 }
 
 pub fn part1() {
     let forms = load_declaration_forms();
-    let yes_answers: Vec<Vec<char>> = forms
-        .into_iter()
-        .map(|df| {
-            df.get_anyones_yes_answered_questions()
-                .into_iter()
-                .collect()
-        })
-        .collect();
-
-    let numbers: Vec<usize> = yes_answers.into_iter().map(|v| v.len()).collect();
-    let summed: usize = numbers.into_iter().sum();
+    let report = SurveyReport::from(&forms).expect("All groups use the default alphabet");
+    let summed: usize = report.groups.iter().map(|g| g.anyone_count).sum();
     println!("The sum is {}", summed);
+    print!("{}", report);
 }
 
 pub fn part2() {
     let forms = load_declaration_forms();
-    let yes_answers: Vec<Vec<char>> = forms
-        .into_iter()
-        .map(|df| {
-            df.get_everyones_yes_answered_questions()
-                .into_iter()
-                .collect()
-        })
-        .collect();
-
-    let numbers: Vec<usize> = yes_answers.into_iter().map(|v| v.len()).collect();
-    let summed: usize = numbers.into_iter().sum();
+    let summed: usize = forms
+        .iter()
+        .map(|df| df.get_everyones_yes_answered_questions().count())
+        .sum();
     println!("The sum is {}", summed);
 }
 
+//Writes the survey report for other tools
+pub fn export() {
+    let forms = load_declaration_forms();
+    let report = SurveyReport::from(&forms).expect("All groups use the default alphabet");
+    export::write_file("./survey.json", report.to_json());
+    export::write_file("./survey_groups.csv", report.groups_to_csv());
+    export::write_file("./survey_questions.csv", report.questions_to_csv());
+}
+
 fn load_declaration_forms() -> Vec<DeclarationFormGrouping> {
     let input = load_input::load_empty_line_seperated("./resources/Day6Input.txt")
         .expect("Failed loading input");
     input
         .into_iter()
-        .map(|input| DeclarationFormGrouping::from(input))
+        .enumerate()
+        .map(|(i, group)| {
+            DeclarationFormGrouping::from(group)
+                .unwrap_or_else(|e| panic!("Failed to parse the group {}: {}", i + 1, e))
+        })
         .collect()
 }
 
@@ -111,15 +432,23 @@ pub mod tests {
     }
 
     fn get_output_anyone(input: Vec<&str>) -> HashSet<char> {
-        DeclarationFormGrouping::from(input.into_iter().map(|s| s.to_string()).collect())
-            .get_anyones_yes_answered_questions()
+        let group =
+            DeclarationFormGrouping::from(input.into_iter().map(|s| s.to_string()).collect())
+                .expect("Failed to parse the group");
+        group
+            .alphabet
+            .letters_of(group.get_anyones_yes_answered_questions())
             .into_iter()
             .collect::<HashSet<_>>()
     }
 
     fn get_output_erveryone(input: Vec<&str>) -> HashSet<char> {
-        DeclarationFormGrouping::from(input.into_iter().map(|s| s.to_string()).collect())
-            .get_everyones_yes_answered_questions()
+        let group =
+            DeclarationFormGrouping::from(input.into_iter().map(|s| s.to_string()).collect())
+                .expect("Failed to parse the group");
+        group
+            .alphabet
+            .letters_of(group.get_everyones_yes_answered_questions())
             .into_iter()
             .collect::<HashSet<_>>()
     }
@@ -232,4 +561,213 @@ pub mod tests {
             0
         );
     }
+
+    #[test]
+    fn test_answer_set() {
+        let alphabet = Alphabet::default();
+        let ab = DeclarationForm::from("ab", &alphabet).unwrap().answered_yes;
+        let bz = DeclarationForm::from("zbb", &alphabet)
+            .unwrap()
+            .answered_yes;
+        assert_eq!(2, ab.count());
+        assert_eq!(vec!['a', 'b', 'z'], alphabet.letters_of(ab.union(bz)));
+        assert_eq!(vec!['b'], alphabet.letters_of(ab.intersection(bz)));
+        assert!(bz.contains(25));
+        assert!(!bz.contains(0));
+        assert!(!bz.contains(64));
+        assert_eq!(26, alphabet.full_set().count());
+
+        let group = DeclarationFormGrouping::from(vec![]).unwrap();
+        assert_eq!(0, group.get_everyones_yes_answered_questions().count());
+    }
+
+    #[test]
+    fn test_unknown_answers() {
+        let input = vec!["ab".to_string(), "aXb".to_string()];
+        assert_eq!(
+            Some(DeclarationError::UnknownAnswer {
+                answer: 'X',
+                line: 2,
+                column: 2
+            }),
+            DeclarationFormGrouping::from(input.to_vec()).err()
+        );
+
+        let alphabet = Alphabet::new("abX").unwrap();
+        let group = DeclarationFormGrouping::from_with_alphabet(input, &alphabet).unwrap();
+        assert_eq!(
+            vec!['a', 'b'],
+            alphabet.letters_of(group.get_everyones_yes_answered_questions())
+        );
+        assert_eq!(3, group.get_anyones_yes_answered_questions().count());
+    }
+
+    #[test]
+    fn test_custom_alphabet() {
+        assert!(Alphabet::new("").is_err());
+        assert!(Alphabet::new("aba").is_err());
+        let letters = ('a'..='z')
+            .chain('A'..='Z')
+            .chain('0'..='9')
+            .chain("+-".chars())
+            .collect::<String>();
+        let alphabet = Alphabet::new(&letters).unwrap();
+        assert_eq!(64, alphabet.len());
+        assert_eq!(64, alphabet.full_set().count());
+        assert!(Alphabet::new(&format!("{}!", letters)).is_err());
+
+        let group = DeclarationFormGrouping::from_with_alphabet(
+            vec!["-Za".to_string(), "a-".to_string()],
+            &alphabet,
+        )
+        .unwrap();
+        assert_eq!(
+            vec!['a', '-'],
+            alphabet.letters_of(group.get_everyones_yes_answered_questions())
+        );
+    }
+
+    #[test]
+    fn test_threshold_queries() {
+        let group = DeclarationFormGrouping::from(
+            ["abc", "ab", "a", "bd"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+        )
+        .unwrap();
+        let letters = |set| group.alphabet().letters_of(set);
+        assert_eq!(4, group.len());
+        assert_eq!(vec![3, 3, 1, 1], group.question_counts()[..4].to_vec());
+        assert_eq!(
+            vec!['a', 'b', 'c', 'd'],
+            letters(group.answered_by_at_least(1))
+        );
+        assert_eq!(vec!['a', 'b'], letters(group.answered_by_at_least(3)));
+        assert!(letters(group.answered_by_at_least(4)).is_empty());
+        assert_eq!(26, group.answered_by_at_least(0).count());
+        assert_eq!(
+            vec!['a', 'b'],
+            letters(group.answered_by_at_least_percent(75))
+        );
+        assert_eq!(
+            vec!['a', 'b'],
+            letters(group.answered_by_at_least_percent(51))
+        );
+        assert_eq!(4, group.answered_by_at_least_percent(25).count());
+        assert_eq!(0, group.answered_by_at_least_percent(101).count());
+        assert_eq!(
+            group.get_everyones_yes_answered_questions(),
+            group.answered_by_at_least_percent(100)
+        );
+        assert_eq!(
+            0,
+            DeclarationFormGrouping::from(vec![])
+                .unwrap()
+                .answered_by_at_least_percent(50)
+                .count()
+        );
+    }
+
+    #[test]
+    fn test_survey_statistics() {
+        let groups = [
+            vec!["abc"],
+            vec!["a", "b", "c"],
+            vec!["ab", "ac"],
+            vec!["b"],
+        ]
+        .iter()
+        .map(|g| DeclarationFormGrouping::from(g.iter().map(|s| s.to_string()).collect()))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+        let statistics = SurveyStatistics::from(&groups).unwrap();
+        assert_eq!(4, statistics.groups);
+        assert_eq!(7, statistics.people);
+        assert_eq!(
+            QuestionFrequency {
+                question: 'b',
+                people: 4,
+                groups_anyone: 4,
+                groups_everyone: 2,
+            },
+            statistics.questions[1]
+        );
+        assert_eq!(
+            vec!['a', 'b'],
+            statistics
+                .most_common()
+                .iter()
+                .map(|q| q.question)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(23, statistics.least_common().len());
+        assert_eq!(0, statistics.least_common()[0].people);
+
+        let other = DeclarationFormGrouping::from_with_alphabet(
+            vec!["x".to_string()],
+            &Alphabet::new("xyz").unwrap(),
+        )
+        .unwrap();
+        assert!(SurveyStatistics::from(&[other]).is_ok());
+        let mut groups = groups;
+        groups.push(
+            DeclarationFormGrouping::from_with_alphabet(
+                vec!["x".to_string()],
+                &Alphabet::new("xyz").unwrap(),
+            )
+            .unwrap(),
+        );
+        assert!(SurveyStatistics::from(&groups).is_err());
+        assert!(SurveyStatistics::from(&[])
+            .unwrap()
+            .most_common()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_survey_report() {
+        let groups = [
+            vec!["abc"],
+            vec!["a", "b", "c"],
+            vec!["ab", "ac"],
+            vec!["b"],
+        ]
+        .iter()
+        .map(|g| DeclarationFormGrouping::from(g.iter().map(|s| s.to_string()).collect()))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+        let report = SurveyReport::from(&groups).unwrap();
+        assert_eq!(
+            GroupReport {
+                group: 3,
+                size: 2,
+                anyone: "abc".to_string(),
+                anyone_count: 3,
+                everyone: "a".to_string(),
+                everyone_count: 1,
+            },
+            report.groups[2]
+        );
+        assert!(report.to_string().starts_with(
+            "7 people in 4 groups answered the survey\nThe most common questions are a, b\n"
+        ));
+
+        let csv = report.groups_to_csv().unwrap();
+        assert_eq!(
+            "group,size,anyone,anyone_count,everyone,everyone_count\n1,1,abc,3,abc,3\n2,3,abc,3,,0\n3,2,abc,3,a,1\n4,1,b,1,b,1\n",
+            csv
+        );
+        let csv = report.questions_to_csv().unwrap();
+        assert!(csv.starts_with(
+            "question,people,groups_anyone,groups_everyone\na,4,3,2\nb,4,4,2\nc,3,3,1\nd,0,0,0\n"
+        ));
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(4, json["groups"].as_array().unwrap().len());
+        assert_eq!("a", json["groups"][2]["everyone"]);
+        assert_eq!(7, json["summary"]["people"]);
+        assert_eq!("c", json["summary"]["questions"][2]["question"]);
+        assert_eq!(3, json["summary"]["questions"][2]["people"]);
+    }
 }
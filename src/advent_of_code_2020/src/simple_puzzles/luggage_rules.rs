@@ -1,189 +1,1018 @@
-use core::slice::Iter;
-use std::collections::HashSet;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+};
 
-use crate::load_input;
+use crate::{export, load_input};
 
-#[derive(Debug)]
-struct LuggageRule {
-    color: String,
-    contains: Option<Vec<ContainingRule>>,
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token<'a> {
+    Word(&'a str),
+    Number(&'a str),
+    Comma,
+    Period,
 }
 
-impl LuggageRule {
-    fn from(input: String) -> Option<LuggageRule> {
-        let rule_in_words: Vec<&str> = input.split_whitespace().collect();
-        let split_index = rule_in_words.iter().position(|w| *w == "bags");
-        match split_index {
-            Some(i) => {
-                let start = String::new();
-                let color = rule_in_words[..i]
-                    .into_iter()
-                    .fold(start, |w1, w2| format!("{} {}", w1, w2))
-                    .trim()
-                    .to_string();
-                let start = i + 2;
-                let contains_input = rule_in_words[start..].into_iter();
-                let contains = ContainingRule::process_multiple(contains_input);
+impl fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "\"{}\"", word),
+            Token::Number(number) => write!(f, "the number {}", number),
+            Token::Comma => write!(f, "\",\""),
+            Token::Period => write!(f, "\".\""),
+        }
+    }
+}
+
+//Errors of a single rule carry the 1-based column of the offending character
+type ColumnError = (usize, String);
 
-                return Some(LuggageRule { color, contains });
+fn tokenize(input: &str) -> Result<Vec<(usize, Token<'_>)>, ColumnError> {
+    let mut tokens = vec![];
+    let mut chars = input.char_indices().enumerate().peekable();
+    while let Some((column, (start, c))) = chars.next() {
+        let column = column + 1;
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            ',' => Token::Comma,
+            '.' => Token::Period,
+            c if c.is_alphanumeric() => {
+                let is_number = c.is_ascii_digit();
+                let mut end = start + c.len_utf8();
+                while let Some((_, (i, next))) = chars.peek() {
+                    if !next.is_alphanumeric() || next.is_ascii_digit() != is_number {
+                        break;
+                    }
+                    end = i + next.len_utf8();
+                    chars.next();
+                }
+                if is_number {
+                    Token::Number(&input[start..end])
+                } else {
+                    Token::Word(&input[start..end])
+                }
             }
-            None => {
-                println!("Failed to parse luggage rule {}", input);
-                return None;
+            c => return Err((column, format!("Unexpected character {}", c))),
+        };
+        tokens.push((column, token));
+    }
+    Ok(tokens)
+}
+
+//rule     := color bag "contain" contents "."?
+//contents := "no" "other" bag | count color bag ("," count color bag)*
+//bag      := "bag" | "bags"
+struct RuleParser<'a> {
+    tokens: Vec<(usize, Token<'a>)>,
+    position: usize,
+    end_column: usize,
+}
+
+impl<'a> RuleParser<'a> {
+    fn from(input: &'a str) -> Result<RuleParser<'a>, ColumnError> {
+        Ok(RuleParser {
+            tokens: tokenize(input)?,
+            position: 0,
+            end_column: input.chars().count() + 1,
+        })
+    }
+
+    fn peek(&self) -> Option<Token<'a>> {
+        self.tokens.get(self.position).map(|(_, t)| *t)
+    }
+
+    fn column(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .map_or(self.end_column, |(c, _)| *c)
+    }
+
+    fn error<T>(&self, expected: &str) -> Result<T, ColumnError> {
+        let found = match self.peek() {
+            Some(token) => token.to_string(),
+            None => "the end of the rule".to_string(),
+        };
+        Err((
+            self.column(),
+            format!("Expected {} but found {}", expected, found),
+        ))
+    }
+
+    fn is_bag(token: Option<Token>) -> bool {
+        matches!(token, Some(Token::Word("bag")) | Some(Token::Word("bags")))
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), ColumnError> {
+        match self.peek() {
+            Some(Token::Word(word)) if word == keyword => {
+                self.position += 1;
+                Ok(())
             }
+            _ => self.error(&format!("\"{}\"", keyword)),
         }
     }
 
-    fn can_carry(&self, color: &str) -> bool {
-        match &self.contains {
-            None => false,
-            Some(rules) => rules.into_iter().any(|r| r.color == color),
+    fn expect_bag(&mut self) -> Result<(), ColumnError> {
+        if RuleParser::is_bag(self.peek()) {
+            self.position += 1;
+            Ok(())
+        } else {
+            self.error("\"bag\" or \"bags\"")
+        }
+    }
+
+    //All words up to "bag" or "bags"
+    fn parse_color(&mut self) -> Result<String, ColumnError> {
+        let mut words = vec![];
+        while let Some(Token::Word(word)) = self.peek() {
+            if RuleParser::is_bag(self.peek()) {
+                break;
+            }
+            words.push(word);
+            self.position += 1;
+        }
+        if words.is_empty() {
+            return self.error("a bag colour");
+        }
+        self.expect_bag()?;
+        Ok(words.join(" "))
+    }
+
+    fn parse_count(&mut self) -> Result<u32, ColumnError> {
+        match self.peek() {
+            Some(Token::Number(number)) => match number.parse::<u32>() {
+                Ok(count) => {
+                    self.position += 1;
+                    Ok(count)
+                }
+                Err(_) => Err((self.column(), format!("The count {} is too large", number))),
+            },
+            _ => self.error("a bag count"),
         }
     }
+
+    fn parse_rule(mut self) -> Result<LuggageRule, ColumnError> {
+        let color = self.parse_color()?;
+        self.expect_keyword("contain")?;
+        let mut contains = vec![];
+        if self.peek() == Some(Token::Word("no")) {
+            self.position += 1;
+            self.expect_keyword("other")?;
+            self.expect_bag()?;
+        } else {
+            loop {
+                let count = self.parse_count()?;
+                let color = self.parse_color()?;
+                contains.push(ContainingRule { color, count });
+                if self.peek() != Some(Token::Comma) {
+                    break;
+                }
+                self.position += 1;
+            }
+        }
+        if self.peek() == Some(Token::Period) {
+            self.position += 1;
+        }
+        if self.peek().is_some() {
+            return self.error("the end of the rule");
+        }
+        Ok(LuggageRule { color, contains })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct RuleParseError {
+    //1-based line of the rule and column within it
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for RuleParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Rule {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct LuggageRule {
+    color: String,
+    contains: Vec<ContainingRule>,
+}
+
+impl LuggageRule {
+    fn from(input: &str) -> Result<LuggageRule, ColumnError> {
+        RuleParser::from(input)?.parse_rule()
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 struct ContainingRule {
     color: String,
     count: u32,
 }
 
-impl ContainingRule {
-    fn from(input: &Vec<&str>) -> Option<ContainingRule> {
-        let count = match input.get(0) {
-            Some(n) => {
-                if n == &"no" {
-                    return None;
-                } else {
-                    match n.parse::<u32>() {
-                        Ok(c) => c,
-                        Err(_) => {
-                            println!("Failed to get count from containing rule: {:?}", input);
-                            return None;
-                        }
-                    }
+//The rules as a directed graph, an edge leads from the outer to the inner bag and is
+//weighted with the number of inner bags. Colours are interned to their index.
+pub struct LuggageRuleSet {
+    colors: Vec<String>,
+    ids: HashMap<String, usize>,
+    contains: Vec<Vec<(usize, u32)>>,
+    contained_in: Vec<Vec<(usize, u32)>>,
+    //Line numbers of the rules defining each colour, only the first definition adds edges
+    definitions: Vec<Vec<usize>>,
+    //Whether a later definition of the colour lists different contents than the first one
+    conflicting: Vec<bool>,
+}
+
+impl LuggageRuleSet {
+    //Empty lines are skipped
+    pub fn from(input: Vec<String>) -> Result<LuggageRuleSet, RuleParseError> {
+        let mut rule_set = LuggageRuleSet {
+            colors: vec![],
+            ids: HashMap::new(),
+            contains: vec![],
+            contained_in: vec![],
+            definitions: vec![],
+            conflicting: vec![],
+        };
+        for (i, line) in input.iter().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match LuggageRule::from(line) {
+                Ok(rule) => rule_set.add_rule(rule, i + 1),
+                Err((column, message)) => {
+                    return Err(RuleParseError {
+                        line: i + 1,
+                        column,
+                        message,
+                    })
                 }
             }
-            None => return None,
+        }
+        Ok(rule_set)
+    }
+
+    fn intern(&mut self, color: String) -> usize {
+        if let Some(id) = self.ids.get(&color) {
+            return *id;
+        }
+        let id = self.colors.len();
+        self.ids.insert(color.to_string(), id);
+        self.colors.push(color);
+        self.contains.push(vec![]);
+        self.contained_in.push(vec![]);
+        self.definitions.push(vec![]);
+        self.conflicting.push(false);
+        id
+    }
+
+    fn add_rule(&mut self, rule: LuggageRule, line: usize) {
+        let outer = self.intern(rule.color);
+        let mut contents = rule
+            .contains
+            .into_iter()
+            .map(|inner| (self.intern(inner.color), inner.count))
+            .collect::<Vec<_>>();
+        self.definitions[outer].push(line);
+        if self.definitions[outer].len() > 1 {
+            let mut first = self.contains[outer].clone();
+            first.sort_unstable();
+            contents.sort_unstable();
+            self.conflicting[outer] |= first != contents;
+            return;
+        }
+        for (inner, count) in contents {
+            self.contains[outer].push((inner, count));
+            self.contained_in[inner].push((outer, count));
+        }
+    }
+
+    fn id(&self, color: &str) -> Option<usize> {
+        self.ids.get(color).copied()
+    }
+
+    fn known_id(&self, color: &str) -> Result<usize, LuggageError> {
+        self.id(color).ok_or_else(|| LuggageError::UnknownColor {
+            color: color.to_string(),
+            suggestion: self.suggest(color).map(|s| s.to_string()),
+        })
+    }
+
+    //The known colour closest to the given one, if it is only a few typos away
+    fn suggest(&self, color: &str) -> Option<&str> {
+        let max_distance = (color.chars().count() / 3).max(1);
+        self.colors
+            .iter()
+            .map(|known| (edit_distance(color, known), known))
+            .filter(|(distance, _)| *distance <= max_distance)
+            .min()
+            .map(|(_, known)| known.as_str())
+    }
+
+    pub fn known_colors(&self) -> Vec<&str> {
+        let mut colors = self.colors.iter().map(|c| c.as_str()).collect::<Vec<_>>();
+        colors.sort_unstable();
+        colors
+    }
+
+    //Bags which contain no other bags
+    pub fn leaf_bags(&self) -> Vec<&str> {
+        self.sorted_colors_where(|id| self.contains[id].is_empty())
+    }
+
+    //Bags which are not contained in any other bag
+    pub fn root_bags(&self) -> Vec<&str> {
+        self.sorted_colors_where(|id| self.contained_in[id].is_empty())
+    }
+
+    fn sorted_colors_where<F>(&self, predicate: F) -> Vec<&str>
+    where
+        F: Fn(usize) -> bool,
+    {
+        let mut colors = (0..self.colors.len())
+            .filter(|id| predicate(*id))
+            .map(|id| self.colors[id].as_str())
+            .collect::<Vec<_>>();
+        colors.sort_unstable();
+        colors
+    }
+
+    //Colours reachable from the start by following the given edges, without the start itself
+    //unless it lies on a cycle
+    fn reachable(&self, start: usize, edges: &[Vec<(usize, u32)>]) -> Vec<bool> {
+        let mut visited = vec![false; self.colors.len()];
+        let mut pending = vec![start];
+        while let Some(id) = pending.pop() {
+            for (next, _) in edges[id].iter() {
+                if !visited[*next] {
+                    visited[*next] = true;
+                    pending.push(*next);
+                }
+            }
+        }
+        visited
+    }
+
+    pub fn all_bags_which_carry(&self, color: &str) -> HashSet<String> {
+        let start = match self.id(color) {
+            None => return HashSet::new(),
+            Some(id) => id,
+        };
+        self.reachable(start, &self.contained_in)
+            .into_iter()
+            .enumerate()
+            .filter(|(_, carries)| *carries)
+            .map(|(id, _)| self.colors[id].to_string())
+            .collect()
+    }
+
+    //Number of bags inside one bag of the given colour, the count of every colour is memoised
+    pub fn containing_count(&self, color: &str) -> Result<u128, LuggageError> {
+        let start = match self.id(color) {
+            None => return Ok(0),
+            Some(id) => id,
+        };
+        let mut memo = vec![None; self.colors.len()];
+        self.count_inside(start, &mut memo)
+    }
+
+    //Iterative depth first search, so long chains of bags do not overflow the call stack. Only a
+    //cycle stops the search, counts which do not fit into a u128 are memoised as None.
+    fn count_inside(
+        &self,
+        start: usize,
+        memo: &mut [Option<Option<u128>>],
+    ) -> Result<u128, LuggageError> {
+        //The colour, the index of its next edge and the bags counted inside so far
+        let mut stack: Vec<(usize, usize, Option<u128>)> = vec![(start, 0, Some(0))];
+        let mut on_path = vec![false; self.colors.len()];
+        on_path[start] = true;
+        //The first colour whose count overflowed, all colours around it overflow as well
+        let mut overflow = None;
+        let add = |count: Option<u128>, nested: Option<u128>, inner_count: u32| {
+            nested?
+                .checked_add(1)?
+                .checked_mul(inner_count as u128)?
+                .checked_add(count?)
         };
+        while let Some((id, next_edge, count)) = stack.last().copied() {
+            match self.contains[id].get(next_edge) {
+                None => {
+                    if count.is_none() && overflow.is_none() {
+                        overflow = Some(id);
+                    }
+                    memo[id] = Some(count);
+                    on_path[id] = false;
+                    stack.pop();
+                    if let Some((outer, edge, outer_count)) = stack.last_mut() {
+                        let (_, inner_count) = self.contains[*outer][*edge];
+                        *outer_count = add(*outer_count, count, inner_count);
+                        *edge += 1;
+                    }
+                }
+                Some((inner, inner_count)) => match memo[*inner] {
+                    Some(nested) => {
+                        let top = stack.last_mut().unwrap();
+                        top.2 = add(count, nested, *inner_count);
+                        top.1 += 1;
+                    }
+                    None if on_path[*inner] => {
+                        let path = stack.iter().map(|(id, _, _)| *id).collect::<Vec<_>>();
+                        let position = path.iter().position(|p| p == inner).unwrap();
+                        return Err(LuggageError::Cycle(self.color_names(&path[position..])));
+                    }
+                    None => {
+                        on_path[*inner] = true;
+                        stack.push((*inner, 0, Some(0)));
+                    }
+                },
+            }
+        }
+        match memo[start] {
+            Some(Some(count)) => Ok(count),
+            _ => Err(LuggageError::Overflow(
+                self.colors[overflow.unwrap_or(start)].to_string(),
+            )),
+        }
+    }
 
-        let start = String::new();
-        let color = input[1..]
+    //Consistency checks of the rules, grouped by kind and sorted by colour
+    pub fn lint(&self) -> Vec<LintIssue> {
+        let ids = self
+            .sorted_colors_where(|_| true)
             .into_iter()
-            .fold(start, |w1, w2| format!("{} {}", w1, w2))
-            .trim()
-            .to_string();
-        Some(ContainingRule { count, color })
-    }
-
-    fn process_multiple(input: Iter<&str>) -> Option<Vec<ContainingRule>> {
-        let mut rule_input: Vec<&str> = vec![];
-        let mut rules: Vec<ContainingRule> = vec![];
-        for word in input {
-            if word.ends_with(",") || word.ends_with(".") {
-                match ContainingRule::from(&rule_input) {
-                    Some(r) => rules.push(r),
-                    None => (),
+            .map(|color| self.ids[color])
+            .collect::<Vec<_>>();
+        let mut issues = vec![];
+        for id in ids.iter().copied() {
+            if self.definitions[id].is_empty() {
+                let mut referenced_by = self.color_names(
+                    &self.contained_in[id]
+                        .iter()
+                        .map(|(outer, _)| *outer)
+                        .collect::<Vec<_>>(),
+                );
+                referenced_by.sort_unstable();
+                referenced_by.dedup();
+                issues.push(LintIssue::UndefinedColor {
+                    color: self.colors[id].to_string(),
+                    referenced_by,
+                });
+            }
+        }
+        for id in ids.iter().copied() {
+            if self.definitions[id].len() > 1 {
+                issues.push(LintIssue::DuplicateDefinition {
+                    color: self.colors[id].to_string(),
+                    lines: self.definitions[id].clone(),
+                    conflicting: self.conflicting[id],
+                });
+            }
+        }
+        //Everything an outermost bag can carry is reachable, the remaining colours are
+        //either on their own or only inside a cycle which no outermost bag leads to
+        let mut reached = vec![false; self.colors.len()];
+        for root in (0..self.colors.len()).filter(|id| self.contained_in[*id].is_empty()) {
+            if self.contains[root].is_empty() {
+                continue;
+            }
+            reached[root] = true;
+            for (id, carried) in self.reachable(root, &self.contains).into_iter().enumerate() {
+                reached[id] |= carried;
+            }
+        }
+        for id in ids.iter().copied() {
+            if !reached[id] {
+                issues.push(LintIssue::Unreachable(self.colors[id].to_string()));
+            }
+        }
+        for id in ids.iter().copied() {
+            if self.reachable(id, &self.contains)[id] {
+                issues.push(LintIssue::SelfContainment(self.colors[id].to_string()));
+            }
+        }
+        issues
+    }
+
+    //Counting the bags inside every colour stops at the first cycle found
+    pub fn check_cycles(&self) -> Result<(), LuggageError> {
+        let mut memo = vec![None; self.colors.len()];
+        for id in 0..self.colors.len() {
+            if memo[id].is_some() {
+                continue;
+            }
+            if let Err(LuggageError::Cycle(colors)) = self.count_inside(id, &mut memo) {
+                return Err(LuggageError::Cycle(colors));
+            }
+        }
+        Ok(())
+    }
+
+    //Graphviz digraph with the counts as edge labels, the query colour of a scope is highlighted
+    pub fn to_dot(&self, scope: DotScope) -> Result<String, LuggageError> {
+        let (included, highlight) = match scope {
+            DotScope::All => (vec![true; self.colors.len()], None),
+            DotScope::ContentsOf(color) | DotScope::ContainersOf(color) => {
+                let id = self.known_id(color)?;
+                let edges = match scope {
+                    DotScope::ContentsOf(_) => &self.contains,
+                    _ => &self.contained_in,
                 };
-                rule_input.clear();
+                let mut included = self.reachable(id, edges);
+                included[id] = true;
+                (included, Some(id))
+            }
+        };
+
+        let mut dot = String::from("digraph luggage {\n");
+        for id in (0..self.colors.len()).filter(|id| included[*id]) {
+            match highlight {
+                Some(h) if h == id => dot.push_str(&format!(
+                    "    \"{}\" [style=filled, fillcolor=gold];\n",
+                    self.colors[id]
+                )),
+                _ => dot.push_str(&format!("    \"{}\";\n", self.colors[id])),
+            }
+        }
+        for outer in (0..self.colors.len()).filter(|id| included[*id]) {
+            for (inner, count) in self.contains[outer].iter() {
+                if included[*inner] {
+                    dot.push_str(&format!(
+                        "    \"{}\" -> \"{}\" [label={}];\n",
+                        self.colors[outer], self.colors[*inner], count
+                    ));
+                }
+            }
+        }
+        dot.push_str("}\n");
+        Ok(dot)
+    }
+
+    //Paths without repeated colours from the outer to the inner bag in depth first order, at most
+    //max_paths of them as shared contents multiply the number of paths
+    pub fn containment_paths(
+        &self,
+        outer: &str,
+        inner: &str,
+        max_paths: usize,
+    ) -> Result<Vec<ContainmentPath>, LuggageError> {
+        let (outer, inner) = (self.known_id(outer)?, self.known_id(inner)?);
+        let mut paths = vec![];
+        //The colours of the current path and the index of their next edge
+        let mut stack = vec![(outer, 0)];
+        let mut on_path = vec![false; self.colors.len()];
+        on_path[outer] = true;
+        while let Some((id, next_edge)) = stack.last().copied() {
+            if paths.len() == max_paths {
+                break;
+            }
+            match self.contains[id].get(next_edge) {
+                None => {
+                    on_path[id] = false;
+                    stack.pop();
+                }
+                Some((next, _)) => {
+                    stack.last_mut().unwrap().1 += 1;
+                    if *next == inner {
+                        let mut colors = stack.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+                        colors.push(inner);
+                        let counts = stack
+                            .iter()
+                            .map(|(id, edge)| self.contains[*id][edge - 1].1)
+                            .collect();
+                        paths.push(ContainmentPath {
+                            colors: self.color_names(&colors),
+                            counts,
+                        });
+                    } else if !on_path[*next] {
+                        on_path[*next] = true;
+                        stack.push((*next, 0));
+                    }
+                }
+            }
+        }
+        Ok(paths)
+    }
+
+    //The path with the fewest nesting levels, found by a breadth first search
+    pub fn shortest_path(
+        &self,
+        outer: &str,
+        inner: &str,
+    ) -> Result<Option<ContainmentPath>, LuggageError> {
+        let (outer, inner) = (self.known_id(outer)?, self.known_id(inner)?);
+        let mut previous: Vec<Option<(usize, u32)>> = vec![None; self.colors.len()];
+        let mut pending = VecDeque::from(vec![outer]);
+        while let Some(id) = pending.pop_front() {
+            for (next, count) in self.contains[id].iter() {
+                if previous[*next].is_some() || (*next == outer && *next != inner) {
+                    continue;
+                }
+                previous[*next] = Some((id, *count));
+                if *next == inner {
+                    pending.clear();
+                    break;
+                }
+                pending.push_back(*next);
+            }
+        }
+
+        let mut colors = vec![inner];
+        let mut counts = vec![];
+        let mut current = inner;
+        loop {
+            match previous[current] {
+                None => return Ok(None),
+                Some((id, count)) => {
+                    colors.push(id);
+                    counts.push(count);
+                    current = id;
+                }
+            }
+            if current == outer {
+                break;
+            }
+        }
+        colors.reverse();
+        counts.reverse();
+        Ok(Some(ContainmentPath {
+            colors: self.color_names(&colors),
+            counts,
+        }))
+    }
+
+    //What a bag contains as a tree, followed by the number of bags on every level. The contents
+    //of a colour are only shown where it appears first and the tree stops at MAX_TREE_DEPTH.
+    pub fn contents_tree(&self, color: &str) -> Result<String, LuggageError> {
+        let id = self.known_id(color)?;
+        let total = self.containing_count(color)?;
+        Ok(self.render_contents_tree(id, total))
+    }
+
+    //The total must be the count of containing_count, which rules out cycles and overflows for
+    //the multiplications below
+    fn render_contents_tree(&self, id: usize, total: u128) -> String {
+        let mut output = format!("{} ({} bags inside)\n", self.colors[id], total);
+        let mut expanded = vec![false; self.colors.len()];
+        expanded[id] = true;
+        let mut pending = vec![];
+        self.push_tree_children(id, 1, "", 0, &mut pending);
+        while let Some(line) = pending.pop() {
+            let TreeLine {
+                id: inner,
+                count,
+                total,
+                prefix,
+                depth,
+                is_last,
+            } = line;
+            let note = if self.contains[inner].is_empty() {
+                ""
+            } else if expanded[inner] {
+                ", contents above"
+            } else if depth + 1 == MAX_TREE_DEPTH {
+                ", contents not shown"
             } else {
-                rule_input.push(word);
+                ""
+            };
+            output.push_str(&format!(
+                "{}{}{} {} ({} in total{})\n",
+                prefix,
+                if is_last { "└── " } else { "├── " },
+                count,
+                self.colors[inner],
+                total,
+                note
+            ));
+            if note.is_empty() && !self.contains[inner].is_empty() {
+                expanded[inner] = true;
+                let prefix = format!("{}{}", prefix, if is_last { "    " } else { "│   " });
+                self.push_tree_children(inner, total, &prefix, depth + 1, &mut pending);
             }
         }
-        if rules.len() == 0 {
-            return None;
-        } else {
-            Some(rules)
+        for (level, count) in self.level_counts(id).iter().enumerate() {
+            output.push_str(&format!("Level {}: {} bags\n", level + 1, count));
+        }
+        output
+    }
+
+    //Pushed in reverse, so the first inner bag is popped first
+    fn push_tree_children(
+        &self,
+        id: usize,
+        multiplier: u128,
+        prefix: &str,
+        depth: usize,
+        pending: &mut Vec<TreeLine>,
+    ) {
+        for (i, (inner, count)) in self.contains[id].iter().rev().enumerate() {
+            pending.push(TreeLine {
+                id: *inner,
+                count: *count,
+                total: multiplier * *count as u128,
+                prefix: prefix.to_string(),
+                depth,
+                is_last: i == 0,
+            });
         }
     }
+
+    //Number of bags on every nesting level, the bags of a colour are merged per level so shared
+    //contents are only visited once. Must not be called on cyclic rules.
+    fn level_counts(&self, id: usize) -> Vec<u128> {
+        let mut levels = vec![];
+        let mut current = HashMap::from([(id, 1u128)]);
+        while !current.is_empty() {
+            let mut next = HashMap::new();
+            let mut level = 0;
+            for (outer, multiplier) in current {
+                for (inner, count) in self.contains[outer].iter() {
+                    let total = multiplier * *count as u128;
+                    *next.entry(*inner).or_insert(0) += total;
+                    level += total;
+                }
+            }
+            if !next.is_empty() {
+                levels.push(level);
+            }
+            current = next;
+        }
+        levels
+    }
+
+    fn color_names(&self, ids: &[usize]) -> Vec<String> {
+        ids.iter().map(|id| self.colors[*id].to_string()).collect()
+    }
 }
 
-struct LuggageRuleSet {
-    rules: Vec<LuggageRule>,
+//A line of the contents tree which is still to be written
+struct TreeLine {
+    id: usize,
+    count: u32,
+    //Bags of this colour inside the outermost bag along this branch
+    total: u128,
+    prefix: String,
+    depth: usize,
+    is_last: bool,
 }
 
-impl LuggageRuleSet {
-    fn from(input: Vec<String>) -> LuggageRuleSet {
-        let rules = input
-            .into_iter()
-            .filter_map(|s| LuggageRule::from(s))
-            .collect();
-        LuggageRuleSet { rules }
-    }
+#[derive(Debug, PartialEq)]
+pub struct ContainmentPath {
+    //From the outer to the inner bag, counts[i] bags of colors[i + 1] are inside colors[i]
+    pub colors: Vec<String>,
+    pub counts: Vec<u32>,
+}
 
-    fn bags_which_carry_direct(&self, color: &String) -> Vec<String> {
-        self.rules
+impl ContainmentPath {
+    //Number of inner bags carried along this path, None on overflow
+    pub fn multiplicity(&self) -> Option<u128> {
+        self.counts
             .iter()
-            .filter(|r| r.can_carry(&color))
-            .map(|r| r.color.to_string())
-            .collect()
+            .try_fold(1u128, |product, count| product.checked_mul(*count as u128))
     }
+}
 
-    fn all_bags_which_carry(&self, color: &String) -> HashSet<String> {
-        let mut carries: HashSet<String> = HashSet::new();
-        let mut new_carries: HashSet<String> =
-            self.bags_which_carry_direct(color).into_iter().collect();
-        while new_carries.len() > 0 {
-            carries.extend(new_carries.iter().cloned());
-            new_carries = new_carries
-                .iter()
-                .flat_map(|c| self.bags_which_carry_direct(c))
-                .filter(|c| !carries.contains(c))
-                .collect();
+impl fmt::Display for ContainmentPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.colors[0])?;
+        for (color, count) in self.colors[1..].iter().zip(self.counts.iter()) {
+            write!(f, " > {} {}", count, color)?;
+        }
+        match self.multiplicity() {
+            Some(multiplicity) => write!(f, " (x{})", multiplicity),
+            None => write!(f, " (too many)"),
         }
-
-        carries
     }
+}
 
-    fn get_containing_rules(&self, color: &String) -> Vec<ContainingRule> {
-        let rules = self.rules.iter().find(|r| &r.color == color);
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DotScope<'a> {
+    All,
+    //The colour and every bag it contains
+    ContentsOf(&'a str),
+    //The colour and every bag which can carry it
+    ContainersOf(&'a str),
+}
 
-        match rules {
-            None => vec![],
-            Some(r) => match &r.contains {
-                None => vec![],
-                Some(c) => c.to_vec(),
-            },
+#[derive(Debug, PartialEq)]
+pub enum LuggageError {
+    UnknownColor {
+        color: String,
+        suggestion: Option<String>,
+    },
+    //The colours of the cycle in containment order
+    Cycle(Vec<String>),
+    Overflow(String),
+}
+
+impl fmt::Display for LuggageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LuggageError::UnknownColor {
+                color,
+                suggestion: Some(suggestion),
+            } => write!(
+                f,
+                "Unknown bag colour {}, did you mean {}?",
+                color, suggestion
+            ),
+            LuggageError::UnknownColor {
+                color,
+                suggestion: None,
+            } => write!(f, "Unknown bag colour {}", color),
+            LuggageError::Cycle(colors) => {
+                write!(f, "The bags contain each other: {}", colors.join(" -> "))
+            }
+            LuggageError::Overflow(color) => {
+                write!(f, "The number of bags inside {} is too large", color)
+            }
         }
     }
+}
 
-    fn containing_count(&self, color: &String) -> u32 {
-        let mut rules: Vec<(u32, Vec<ContainingRule>)> =
-            vec![(1, self.get_containing_rules(color))];
-        let mut count: u32 = 0;
+#[derive(Debug, PartialEq)]
+pub enum LintIssue {
+    //A colour which is inside other bags but has no rule of its own
+    UndefinedColor {
+        color: String,
+        referenced_by: Vec<String>,
+    },
+    //A colour with several rules, conflicting when their contents differ
+    DuplicateDefinition {
+        color: String,
+        lines: Vec<usize>,
+        conflicting: bool,
+    },
+    //A colour which no outermost bag can carry and which carries nothing itself or only
+    //bags of a cycle
+    Unreachable(String),
+    //A colour which contains itself, directly or through other bags
+    SelfContainment(String),
+}
 
-        while rules.len() > 0 {
-            let mut next_rules: Vec<(u32, Vec<ContainingRule>)> = vec![];
-            for (multiplier, rules) in rules {
-                for rule in rules {
-                    let nested_rules = self.get_containing_rules(&rule.color);
-                    count += rule.count * multiplier;
-                    if nested_rules.len() > 0 {
-                        next_rules.push((rule.count * multiplier, nested_rules));
-                    }
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LintIssue::UndefinedColor {
+                color,
+                referenced_by,
+            } => write!(
+                f,
+                "{} bags have no rule but are inside {}",
+                color,
+                referenced_by.join(", ")
+            ),
+            LintIssue::DuplicateDefinition {
+                color,
+                lines,
+                conflicting,
+            } => {
+                let lines = lines.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+                write!(
+                    f,
+                    "{} bags are defined by rules {}",
+                    color,
+                    lines.join(", ")
+                )?;
+                if *conflicting {
+                    write!(f, " with different contents")?;
                 }
+                Ok(())
             }
-            rules = next_rules;
+            LintIssue::Unreachable(color) => {
+                write!(f, "{} bags cannot be reached from any outermost bag", color)
+            }
+            LintIssue::SelfContainment(color) => write!(f, "{} bags can contain themselves", color),
         }
+    }
+}
 
-        count
+//Levenshtein distance over chars
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + (ca != *cb) as usize;
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
     }
+    previous[b.len()]
 }
 
+pub const DEFAULT_COLOR: &str = "shiny gold";
+const MAX_TREE_DEPTH: usize = 32;
+const MAX_EXPLAINED_PATHS: usize = 10;
+
 pub fn part1() {
-    let input =
-        load_input::load_strings("./resources/Day7Input.txt").expect("Failed to load input");
-    let rule_set = LuggageRuleSet::from(input);
-    let bags = rule_set.all_bags_which_carry(&"shiny gold".to_string());
-    println!("{} bags can contain a shiny gold bag", bags.len());
+    count_containers(DEFAULT_COLOR);
 }
 
 pub fn part2() {
+    count_contents(DEFAULT_COLOR);
+}
+
+pub fn count_containers(color: &str) {
+    let rule_set = load_rule_set();
+    print_warnings(&rule_set);
+    if let Err(e) = rule_set.known_id(color) {
+        println!("{}", e);
+        return;
+    }
+    let bags = rule_set.all_bags_which_carry(color);
+    println!("{} bags can contain a {} bag", bags.len(), color);
+}
+
+//How the inner bag ends up inside the outer bag, the shortest path and the first other paths
+pub fn explain_containment(outer: &str, inner: &str) {
+    let rule_set = load_rule_set();
+    match rule_set.shortest_path(outer, inner) {
+        Ok(Some(path)) => println!("Shortest path: {}", path),
+        Ok(None) => {
+            println!("{} bags cannot carry a {} bag", outer, inner);
+            return;
+        }
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    }
+    match rule_set.containment_paths(outer, inner, MAX_EXPLAINED_PATHS + 1) {
+        Ok(paths) => {
+            println!("Paths:");
+            for path in paths.iter().take(MAX_EXPLAINED_PATHS) {
+                println!("{}", path);
+            }
+            if paths.len() > MAX_EXPLAINED_PATHS {
+                println!("...");
+            }
+        }
+        Err(e) => println!("{}", e),
+    }
+}
+
+pub fn count_contents(color: &str) {
+    let rule_set = load_rule_set();
+    print_warnings(&rule_set);
+    let id = match rule_set.known_id(color) {
+        Ok(id) => id,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    match rule_set.containing_count(color) {
+        Ok(bags) => {
+            println!("One {} bag must contain {} other bags", color, bags);
+            print!("{}", rule_set.render_contents_tree(id, bags));
+        }
+        Err(e) => println!("{}", e),
+    }
+}
+
+pub fn list_colors() {
+    let rule_set = load_rule_set();
+    println!("Known colours: {}", rule_set.known_colors().join(", "));
+    println!("Leaf bags: {}", rule_set.leaf_bags().join(", "));
+    println!("Root bags: {}", rule_set.root_bags().join(", "));
+}
+
+//Writes the rule graph in the scope to bags.dot, render it with: dot -Tsvg bags.dot -o bags.svg
+pub fn write_dot(scope: DotScope) {
+    let rule_set = load_rule_set();
+    let dot = rule_set.to_dot(scope).map_err(|e| e.to_string());
+    export::write_file("./bags.dot", dot);
+}
+
+pub fn lint_rules() {
+    let issues = load_rule_set().lint();
+    if issues.is_empty() {
+        println!("The luggage rules are consistent");
+    }
+    for issue in issues {
+        println!("{}", issue);
+    }
+}
+
+fn print_warnings(rule_set: &LuggageRuleSet) {
+    for issue in rule_set.lint() {
+        println!("Warning: {}", issue);
+    }
+}
+
+fn load_rule_set() -> LuggageRuleSet {
     let input =
-    load_input::load_strings("./resources/Day7Input.txt").expect("Failed to load input");
-    let rule_set = LuggageRuleSet::from(input);
-    let bags = rule_set.containing_count(&"shiny gold".to_string());
-    println!("One shiny gold bag must contain {} other bags", bags);
+        load_input::load_strings("./resources/Day7Input.txt").expect("Failed to load input");
+    LuggageRuleSet::from(input)
+        .unwrap_or_else(|e| panic!("Failed to parse the luggage rules: {}", e))
 }
 
 #[cfg(test)]
@@ -219,16 +1048,543 @@ pub mod test {
     #[test]
     fn test_can_carry_count() {
         let input = get_input_1();
-        let rule_set = LuggageRuleSet::from(input);
-        let bags = rule_set.all_bags_which_carry(&"shiny gold".to_string());
+        let rule_set = LuggageRuleSet::from(input).expect("Failed to parse the rules");
+        let bags = rule_set.all_bags_which_carry("shiny gold");
         assert_eq!(4, bags.len());
     }
 
     #[test]
     fn test_carries_count() {
         let input = get_input_2();
-        let rule_set = LuggageRuleSet::from(input);
-        let bags = rule_set.containing_count(&"shiny gold".to_string());
-        assert_eq!(126, bags);
+        let rule_set = LuggageRuleSet::from(input).expect("Failed to parse the rules");
+        let bags = rule_set.containing_count("shiny gold");
+        assert_eq!(Ok(126), bags);
+    }
+
+    #[test]
+    fn test_parse_rule() {
+        let rule =
+            LuggageRule::from("light red bags contain 1 bright white bag, 2 muted yellow bags.")
+                .unwrap();
+        assert_eq!(
+            LuggageRule {
+                color: "light red".to_string(),
+                contains: vec![
+                    ContainingRule {
+                        color: "bright white".to_string(),
+                        count: 1
+                    },
+                    ContainingRule {
+                        color: "muted yellow".to_string(),
+                        count: 2
+                    },
+                ],
+            },
+            rule
+        );
+        //Singular and plural, any whitespace and no trailing period
+        assert_eq!(
+            Ok(rule),
+            LuggageRule::from(
+                "  light   red bag contain\t1 bright white bags ,2 muted yellow bag "
+            )
+        );
+        assert_eq!(
+            Ok(LuggageRule {
+                color: "faded blue".to_string(),
+                contains: vec![]
+            }),
+            LuggageRule::from("faded blue bags contain no other bag")
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |input: &str| LuggageRule::from(input).unwrap_err();
+        assert_eq!(
+            (
+                17,
+                "Expected \"contain\" but found \"contains\"".to_string()
+            ),
+            error("faded blue bags contains no other bags.")
+        );
+        assert_eq!(
+            (25, "Expected a bag count but found \"one\"".to_string()),
+            error("faded blue bags contain one dark red bag.")
+        );
+        assert_eq!(
+            (
+                35,
+                "Expected \"bag\" or \"bags\" but found the end of the rule".to_string()
+            ),
+            error("faded blue bags contain 1 dark red")
+        );
+        assert_eq!(
+            (1, "Expected a bag colour but found \"bags\"".to_string()),
+            error("bags contain no other bags.")
+        );
+        assert_eq!(
+            (
+                36,
+                "Expected the end of the rule but found the number 2".to_string()
+            ),
+            error("faded blue bags contain 1 red bag. 2 dark red bags.")
+        );
+        assert_eq!(
+            (28, "Unexpected character ;".to_string()),
+            error("faded blue bags contain 1 a; bag")
+        );
+        assert_eq!(
+            (25, "The count 99999999999 is too large".to_string()),
+            error("faded blue bags contain 99999999999 red bags")
+        );
+
+        let input = vec![
+            "faded blue bags contain no other bags.".to_string(),
+            "".to_string(),
+            "dotted black bags contain 2 faded blue.".to_string(),
+        ];
+        let error = LuggageRuleSet::from(input).err().unwrap();
+        assert_eq!(
+            "Rule 3, column 39: Expected \"bag\" or \"bags\" but found \".\"",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn test_rule_graph() {
+        let rule_set = LuggageRuleSet::from(get_input_1()).expect("Failed to parse the rules");
+        assert_eq!(9, rule_set.colors.len());
+        let id = |color| rule_set.id(color).unwrap();
+        assert_eq!("muted yellow", rule_set.colors[id("muted yellow")]);
+        assert_eq!(None, rule_set.id("shiny blue"));
+        assert_eq!(
+            vec![(id("shiny gold"), 2), (id("faded blue"), 9)],
+            rule_set.contains[id("muted yellow")]
+        );
+        assert_eq!(
+            vec![(id("bright white"), 1), (id("muted yellow"), 2)],
+            rule_set.contained_in[id("shiny gold")]
+        );
+        assert!(rule_set.contains[id("faded blue")].is_empty());
+        assert!(rule_set.all_bags_which_carry("light red").is_empty());
+        assert_eq!(Ok(32), rule_set.containing_count("shiny gold"));
+        assert_eq!(Ok(0), rule_set.containing_count("shiny blue"));
+    }
+
+    //Colours only consist of words, so the digits of the level are spelled as letters k-t
+    fn level(i: usize) -> String {
+        let digits = i
+            .to_string()
+            .bytes()
+            .map(|d| (d - b'0' + b'k') as char)
+            .collect::<String>();
+        format!("level {}", digits)
+    }
+
+    fn get_chain(length: usize) -> Vec<String> {
+        let mut rules = (0..length)
+            .map(|i| format!("{} bags contain 2 {} bags.", level(i), level(i + 1)))
+            .collect::<Vec<_>>();
+        rules.push(format!("{} bags contain no other bags.", level(length)));
+        rules
+    }
+
+    #[test]
+    fn test_long_chain() {
+        let length = 100_000;
+        let mut rules = (0..length)
+            .map(|i| format!("{} bags contain 1 {} bag.", level(i), level(i + 1)))
+            .collect::<Vec<_>>();
+        rules.push(format!("{} bags contain no other bags.", level(length)));
+        let rule_set = LuggageRuleSet::from(rules).expect("Failed to parse the rules");
+        assert_eq!(Ok(length as u128), rule_set.containing_count(&level(0)));
+        assert_eq!(Ok(1), rule_set.containing_count(&level(length - 1)));
+
+        let paths = rule_set
+            .containment_paths(&level(0), &level(length), 10)
+            .unwrap();
+        assert_eq!(1, paths.len());
+        assert_eq!(length + 1, paths[0].colors.len());
+        assert_eq!(Some(1), paths[0].multiplicity());
+
+        let rule_set = LuggageRuleSet::from(get_chain(length)).expect("Failed to parse the rules");
+        assert_eq!(
+            Err(LuggageError::Overflow(level(length - 128))),
+            rule_set.containing_count(&level(0))
+        );
+    }
+
+    #[test]
+    fn test_cycles() {
+        let input = vec![
+            "shiny gold bags contain 1 dark red bag, 2 faded blue bags.".to_string(),
+            "dark red bags contain 2 dark orange bags.".to_string(),
+            "dark orange bags contain 1 dark red bag.".to_string(),
+            "faded blue bags contain no other bags.".to_string(),
+        ];
+        let rule_set = LuggageRuleSet::from(input).expect("Failed to parse the rules");
+        let cycle = LuggageError::Cycle(vec!["dark red".to_string(), "dark orange".to_string()]);
+        assert_eq!(Err(cycle), rule_set.containing_count("shiny gold"));
+        assert_eq!(Ok(0), rule_set.containing_count("faded blue"));
+        assert_eq!(
+            "The bags contain each other: dark red -> dark orange",
+            rule_set.check_cycles().unwrap_err().to_string()
+        );
+
+        let input = vec!["shiny gold bags contain 1 shiny gold bag.".to_string()];
+        let rule_set = LuggageRuleSet::from(input).expect("Failed to parse the rules");
+        assert_eq!(
+            Err(LuggageError::Cycle(vec!["shiny gold".to_string()])),
+            rule_set.check_cycles()
+        );
+
+        let rule_set = LuggageRuleSet::from(get_input_1()).expect("Failed to parse the rules");
+        assert_eq!(Ok(()), rule_set.check_cycles());
+
+        //The cycle is found behind a count which overflows
+        let mut input = get_chain(128);
+        input[0] = format!(
+            "{} bags contain 2 {} bags, 1 dark red bag.",
+            level(0),
+            level(1)
+        );
+        input.push("dark red bags contain 2 dark orange bags.".to_string());
+        input.push("dark orange bags contain 1 dark red bag.".to_string());
+        let rule_set = LuggageRuleSet::from(input).expect("Failed to parse the rules");
+        let cycle = LuggageError::Cycle(vec!["dark red".to_string(), "dark orange".to_string()]);
+        assert_eq!(Err(cycle), rule_set.containing_count(&level(0)));
+        assert_eq!(
+            "The bags contain each other: dark red -> dark orange",
+            rule_set.check_cycles().unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_deep_nesting() {
+        let rule_set = LuggageRuleSet::from(get_chain(100)).expect("Failed to parse the rules");
+        //2 + 4 + ... + 2^100
+        assert_eq!(Ok((1u128 << 101) - 2), rule_set.containing_count(&level(0)));
+
+        let rule_set = LuggageRuleSet::from(get_chain(128)).expect("Failed to parse the rules");
+        assert_eq!(
+            Err(LuggageError::Overflow(level(0))),
+            rule_set.containing_count(&level(0))
+        );
+        assert_eq!(Ok(u128::MAX - 1), rule_set.containing_count(&level(1)));
+    }
+
+    #[test]
+    fn test_dot_export() {
+        let rule_set = LuggageRuleSet::from(get_input_1()).expect("Failed to parse the rules");
+        assert_eq!(
+            Ok(concat!(
+                "digraph luggage {\n",
+                "    \"light red\";\n",
+                "    \"bright white\";\n",
+                "    \"muted yellow\";\n",
+                "    \"dark orange\";\n",
+                "    \"shiny gold\" [style=filled, fillcolor=gold];\n",
+                "    \"light red\" -> \"bright white\" [label=1];\n",
+                "    \"light red\" -> \"muted yellow\" [label=2];\n",
+                "    \"bright white\" -> \"shiny gold\" [label=1];\n",
+                "    \"muted yellow\" -> \"shiny gold\" [label=2];\n",
+                "    \"dark orange\" -> \"bright white\" [label=3];\n",
+                "    \"dark orange\" -> \"muted yellow\" [label=4];\n",
+                "}\n"
+            )
+            .to_string()),
+            rule_set.to_dot(DotScope::ContainersOf("shiny gold"))
+        );
+
+        let contents = rule_set.to_dot(DotScope::ContentsOf("dark olive")).unwrap();
+        //Three bags and the two lines of the graph itself
+        assert_eq!(5, contents.lines().filter(|l| !l.contains("->")).count());
+        assert!(contents.contains("    \"dark olive\" [style=filled, fillcolor=gold];\n"));
+        assert!(contents.contains("    \"dark olive\" -> \"dotted black\" [label=4];\n"));
+        assert!(!contents.contains("shiny gold"));
+
+        let all = rule_set.to_dot(DotScope::All).unwrap();
+        assert_eq!(9 + 13 + 2, all.lines().count());
+        assert!(!all.contains("fillcolor"));
+        assert_eq!(
+            Err(LuggageError::UnknownColor {
+                color: "shiny blue".to_string(),
+                suggestion: None
+            }),
+            rule_set.to_dot(DotScope::ContentsOf("shiny blue"))
+        );
+    }
+
+    #[test]
+    fn test_containment_paths() {
+        let rule_set = LuggageRuleSet::from(get_input_1()).expect("Failed to parse the rules");
+        let paths = rule_set
+            .containment_paths("light red", "faded blue", 10)
+            .unwrap()
+            .iter()
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                "light red > 1 bright white > 1 shiny gold > 1 dark olive > 3 faded blue (x3)",
+                "light red > 1 bright white > 1 shiny gold > 2 vibrant plum > 5 faded blue (x10)",
+                "light red > 2 muted yellow > 2 shiny gold > 1 dark olive > 3 faded blue (x12)",
+                "light red > 2 muted yellow > 2 shiny gold > 2 vibrant plum > 5 faded blue (x40)",
+                "light red > 2 muted yellow > 9 faded blue (x18)",
+            ],
+            paths
+        );
+        assert_eq!(
+            paths[..2].iter().map(|p| p.as_str()).collect::<Vec<_>>(),
+            rule_set
+                .containment_paths("light red", "faded blue", 2)
+                .unwrap()
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            Some(ContainmentPath {
+                colors: vec![
+                    "light red".to_string(),
+                    "muted yellow".to_string(),
+                    "faded blue".to_string()
+                ],
+                counts: vec![2, 9],
+            }),
+            rule_set.shortest_path("light red", "faded blue").unwrap()
+        );
+        assert_eq!(
+            None,
+            rule_set.shortest_path("faded blue", "light red").unwrap()
+        );
+        assert_eq!(
+            None,
+            rule_set.shortest_path("shiny gold", "shiny gold").unwrap()
+        );
+        assert!(rule_set
+            .containment_paths("shiny gold", "light red", 10)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            Err(LuggageError::UnknownColor {
+                color: "shiny blue".to_string(),
+                suggestion: None
+            }),
+            rule_set.containment_paths("shiny blue", "light red", 10)
+        );
+
+        let input = vec![
+            "dark red bags contain 2 dark orange bags.".to_string(),
+            "dark orange bags contain 3 dark red bags.".to_string(),
+        ];
+        let rule_set = LuggageRuleSet::from(input).expect("Failed to parse the rules");
+        assert_eq!(
+            "dark red > 2 dark orange > 3 dark red (x6)",
+            rule_set
+                .shortest_path("dark red", "dark red")
+                .unwrap()
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            1,
+            rule_set
+                .containment_paths("dark red", "dark red", 10)
+                .unwrap()
+                .len()
+        );
+    }
+
+    #[test]
+    fn test_contents_tree() {
+        let rule_set = LuggageRuleSet::from(get_input_1()).expect("Failed to parse the rules");
+        assert_eq!(
+            Ok(concat!(
+                "shiny gold (32 bags inside)\n",
+                "├── 1 dark olive (1 in total)\n",
+                "│   ├── 3 faded blue (3 in total)\n",
+                "│   └── 4 dotted black (4 in total)\n",
+                "└── 2 vibrant plum (2 in total)\n",
+                "    ├── 5 faded blue (10 in total)\n",
+                "    └── 6 dotted black (12 in total)\n",
+                "Level 1: 3 bags\n",
+                "Level 2: 29 bags\n",
+            )
+            .to_string()),
+            rule_set.contents_tree("shiny gold")
+        );
+        assert_eq!(
+            Ok("faded blue (0 bags inside)\n".to_string()),
+            rule_set.contents_tree("faded blue")
+        );
+    }
+
+    #[test]
+    fn test_contents_tree_of_shared_bags() {
+        //Every level holds a left and a right bag which both contain the next level
+        let length = 40;
+        let side = |name: &str, i: usize| level(i).replace("level", name);
+        let mut rules = (0..length)
+            .flat_map(|i| {
+                vec![
+                    format!(
+                        "{} bags contain 1 {} bag, 1 {} bag.",
+                        level(i),
+                        side("left", i),
+                        side("right", i)
+                    ),
+                    format!("{} bags contain 1 {} bag.", side("left", i), level(i + 1)),
+                    format!("{} bags contain 1 {} bag.", side("right", i), level(i + 1)),
+                ]
+            })
+            .collect::<Vec<_>>();
+        rules.push(format!("{} bags contain no other bags.", level(length)));
+        let rule_set = LuggageRuleSet::from(rules).expect("Failed to parse the rules");
+        let tree = rule_set.contents_tree(&level(0)).unwrap();
+        let lines = tree.lines().collect::<Vec<_>>();
+        //The header, two lines per level down to the depth limit and one line per level
+        assert_eq!(1 + 2 * 32 + 2 * length, lines.len());
+        assert_eq!(format!("│   └── 1 {} (1 in total)", level(1)), lines[2]);
+        assert!(lines[32].ends_with("contents not shown)"));
+        assert_eq!(
+            format!("    └── 1 {} (1 in total, contents above)", level(1)),
+            lines[64]
+        );
+        assert_eq!("Level 1: 2 bags", lines[65]);
+        assert_eq!("Level 2: 2 bags", lines[66]);
+        assert_eq!(
+            format!("Level {}: {} bags", 2 * length, 1u128 << length),
+            lines[144]
+        );
+        let levels = lines[65..]
+            .iter()
+            .map(|l| l.split(' ').nth(2).unwrap().parse::<u128>().unwrap())
+            .sum::<u128>();
+        assert_eq!(Ok(levels), rule_set.containing_count(&level(0)));
+
+        //There are 2^40 paths through all levels
+        let paths = rule_set
+            .containment_paths(&level(0), &level(length), 100)
+            .unwrap();
+        assert_eq!(100, paths.len());
+        assert!(paths.iter().all(|p| p.colors.len() == 2 * length + 1));
+    }
+
+    #[test]
+    fn test_color_queries() {
+        let rule_set = LuggageRuleSet::from(get_input_1()).expect("Failed to parse the rules");
+        assert_eq!(9, rule_set.known_colors().len());
+        assert_eq!("bright white", rule_set.known_colors()[0]);
+        assert_eq!(vec!["dotted black", "faded blue"], rule_set.leaf_bags());
+        assert_eq!(vec!["dark orange", "light red"], rule_set.root_bags());
+    }
+
+    #[test]
+    fn test_color_suggestions() {
+        let rule_set = LuggageRuleSet::from(get_input_1()).expect("Failed to parse the rules");
+        assert_eq!(0, edit_distance("shiny gold", "shiny gold"));
+        assert_eq!(3, edit_distance("kitten", "sitting"));
+        assert_eq!(4, edit_distance("", "gold"));
+
+        assert_eq!(
+            "Unknown bag colour shiny glod, did you mean shiny gold?",
+            rule_set.known_id("shiny glod").unwrap_err().to_string()
+        );
+        assert_eq!(
+            Err(LuggageError::UnknownColor {
+                color: "dark olives".to_string(),
+                suggestion: Some("dark olive".to_string())
+            }),
+            rule_set.known_id("dark olives")
+        );
+        assert_eq!(
+            "Unknown bag colour pink",
+            rule_set.known_id("pink").unwrap_err().to_string()
+        );
+        assert_eq!(Ok(4), rule_set.known_id("shiny gold"));
+    }
+
+    #[test]
+    fn test_lint() {
+        let rule_set = LuggageRuleSet::from(get_input_1()).expect("Failed to parse the rules");
+        assert_eq!(Vec::<LintIssue>::new(), rule_set.lint());
+
+        let input = vec![
+            "shiny gold bags contain 1 dark red bag, 2 faded blue bags.".to_string(),
+            "dark red bags contain 2 dark orange bags.".to_string(),
+            "faded blue bags contain no other bags.".to_string(),
+            "shiny gold bags contain 2 faded blue bags, 1 dark red bag.".to_string(),
+            "dark red bags contain 3 dark orange bags.".to_string(),
+            "pale green bags contain no other bags.".to_string(),
+            "wavy teal bags contain 1 wavy teal bag.".to_string(),
+        ];
+        let rule_set = LuggageRuleSet::from(input).expect("Failed to parse the rules");
+        let issues = rule_set.lint();
+        assert_eq!(
+            vec![
+                LintIssue::UndefinedColor {
+                    color: "dark orange".to_string(),
+                    referenced_by: vec!["dark red".to_string()]
+                },
+                LintIssue::DuplicateDefinition {
+                    color: "dark red".to_string(),
+                    lines: vec![2, 5],
+                    conflicting: true
+                },
+                LintIssue::DuplicateDefinition {
+                    color: "shiny gold".to_string(),
+                    lines: vec![1, 4],
+                    conflicting: false
+                },
+                LintIssue::Unreachable("pale green".to_string()),
+                LintIssue::Unreachable("wavy teal".to_string()),
+                LintIssue::SelfContainment("wavy teal".to_string()),
+            ],
+            issues
+        );
+        assert_eq!(
+            "dark orange bags have no rule but are inside dark red",
+            issues[0].to_string()
+        );
+        assert_eq!(
+            "dark red bags are defined by rules 2, 5 with different contents",
+            issues[1].to_string()
+        );
+        assert_eq!(
+            "shiny gold bags are defined by rules 1, 4",
+            issues[2].to_string()
+        );
+        assert_eq!(
+            "pale green bags cannot be reached from any outermost bag",
+            issues[3].to_string()
+        );
+        assert_eq!(
+            "wavy teal bags can contain themselves",
+            issues[5].to_string()
+        );
+        //Only the first definition counts
+        assert_eq!(Ok(5), rule_set.containing_count("shiny gold"));
+    }
+
+    #[test]
+    fn test_lint_unreachable_cycle() {
+        let input = vec![
+            "dark red bags contain 2 dark orange bags.".to_string(),
+            "dark orange bags contain 1 dark red bag, 1 faded blue bag.".to_string(),
+            "faded blue bags contain no other bags.".to_string(),
+        ];
+        let rule_set = LuggageRuleSet::from(input).expect("Failed to parse the rules");
+        assert_eq!(
+            vec![
+                LintIssue::Unreachable("dark orange".to_string()),
+                LintIssue::Unreachable("dark red".to_string()),
+                LintIssue::Unreachable("faded blue".to_string()),
+                LintIssue::SelfContainment("dark orange".to_string()),
+                LintIssue::SelfContainment("dark red".to_string()),
+            ],
+            rule_set.lint()
+        );
     }
 }
@@ -1,202 +1,775 @@
-use crate::load_input;
+use std::{collections::BTreeMap, fmt, fs, ops::RangeInclusive, sync::Arc};
+
 use regex::Regex;
+use serde::{ser::SerializeMap, Deserialize, Serialize, Serializer};
+
+use crate::{export, load_input, pattern_cache};
+
+const NORTH_POLE_SCHEMA: &str = include_str!("../../resources/PassportSchema.json");
+
+//Heights are compared in micrometres so fractional values of every unit stay exact
+const MICROMETRES_PER_MILLIMETRE: i64 = 1_000;
+const MAX_FRACTION_DIGITS: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HeightUnit {
+    Millimeter,
+    Centimeter,
+    Meter,
+    Inch,
+    //Either written as 5.5ft or as 5'6"
+    Foot,
+}
+
+impl HeightUnit {
+    fn from(input: &str) -> Option<HeightUnit> {
+        match input {
+            "mm" => Some(HeightUnit::Millimeter),
+            "cm" => Some(HeightUnit::Centimeter),
+            "m" => Some(HeightUnit::Meter),
+            "in" => Some(HeightUnit::Inch),
+            "ft" => Some(HeightUnit::Foot),
+            _ => None,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            HeightUnit::Millimeter => "mm",
+            HeightUnit::Centimeter => "cm",
+            HeightUnit::Meter => "m",
+            HeightUnit::Inch => "in",
+            HeightUnit::Foot => "ft",
+        }
+    }
 
-#[derive(PartialEq)]
-enum Height {
-    Inch(i32),
-    CM(i32),
-    Ivalid,
-    NotFound,
+    fn micrometres(&self) -> i64 {
+        match self {
+            HeightUnit::Millimeter => MICROMETRES_PER_MILLIMETRE,
+            HeightUnit::Centimeter => 10 * MICROMETRES_PER_MILLIMETRE,
+            HeightUnit::Meter => 1_000 * MICROMETRES_PER_MILLIMETRE,
+            HeightUnit::Inch => 25_400,
+            HeightUnit::Foot => 12 * 25_400,
+        }
+    }
 }
 
-struct Passport {
-    birth_year: Option<i32>,
-    issue_year: Option<i32>,
-    expiration_year: Option<i32>,
-    height: Height,
-    hair_color: Option<String>,
-    eye_color: Option<String>,
-    passport_id: Option<String>,
-    country_id: Option<String>,
+#[derive(Debug, Clone, PartialEq)]
+pub struct Height {
+    micrometres: i64,
+    //The value as written in the passport, used for reporting
+    text: String,
+    pub unit: HeightUnit,
 }
 
-impl Passport {
-    fn from<T>(input: Vec<(T, T)>) -> Passport
-    where
-        T: Into<String>,
+impl Height {
+    pub fn from(input: &str) -> Option<Height> {
+        let (micrometres, unit) = match input.split_once('\'') {
+            Some((feet, inches)) => (
+                Height::parse_feet_and_inches(feet, inches)?,
+                HeightUnit::Foot,
+            ),
+            None => {
+                let split_index = input.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+                let (value, unit) = input.split_at(split_index);
+                let unit = HeightUnit::from(unit)?;
+                (parse_scaled(value, unit.micrometres())?, unit)
+            }
+        };
+        Some(Height {
+            micrometres,
+            text: input.to_string(),
+            unit,
+        })
+    }
+
+    //5'11" or 6', the inch mark is optional
+    fn parse_feet_and_inches(feet: &str, inches: &str) -> Option<i64> {
+        if feet.is_empty() || !feet.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let feet = feet
+            .parse::<i64>()
+            .ok()?
+            .checked_mul(HeightUnit::Foot.micrometres())?;
+        let inches = inches.strip_suffix('"').unwrap_or(inches);
+        if inches.is_empty() {
+            return Some(feet);
+        }
+        let inches = parse_scaled(inches, HeightUnit::Inch.micrometres())?;
+        if inches >= HeightUnit::Foot.micrometres() {
+            return None;
+        }
+        feet.checked_add(inches)
+    }
+
+    pub fn micrometres(&self) -> i64 {
+        self.micrometres
+    }
+
+    pub fn in_unit(&self, unit: HeightUnit) -> f64 {
+        self.micrometres as f64 / unit.micrometres() as f64
+    }
+
+    //Exact decimal representation like 187.96cm, without trailing zeros
+    pub fn to_centimetres_string(&self) -> String {
+        let per_centimetre = HeightUnit::Centimeter.micrometres();
+        let whole = self.micrometres / per_centimetre;
+        let fraction = self.micrometres % per_centimetre;
+        if fraction == 0 {
+            return format!("{}cm", whole);
+        }
+        let fraction = format!("{:04}", fraction);
+        format!("{}.{}cm", whole, fraction.trim_end_matches('0'))
+    }
+}
+
+impl fmt::Display for Height {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+//Parses a non negative decimal number like 1.83 and multiplies it by the given factor
+fn parse_scaled(number: &str, factor: i64) -> Option<i64> {
+    let (integer, fraction) = number.split_once('.').unwrap_or((number, ""));
+    let is_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    if integer.is_empty()
+        || !is_digits(integer)
+        || !is_digits(fraction)
+        || fraction.len() > MAX_FRACTION_DIGITS
+        || (number.contains('.') && fraction.is_empty())
     {
-        let mut birth_year: Option<i32> = None;
-        let mut issue_year: Option<i32> = None;
-        let mut expiration_year: Option<i32> = None;
-        let mut height: Height = Height::NotFound;
-        let mut hair_color: Option<String> = None;
-        let mut eye_color: Option<String> = None;
-        let mut passport_id: Option<String> = None;
-        let mut country_id: Option<String> = None;
-
-        for (k, v) in input {
-            match k.into().as_str() {
-                "byr" => {
-                    birth_year = match v.into().parse::<i32>() {
-                        Ok(num) => Some(num),
-                        Err(_) => None,
-                    }
-                }
-                "iyr" => {
-                    issue_year = match v.into().parse::<i32>() {
-                        Ok(num) => Some(num),
-                        Err(_) => None,
-                    }
-                }
-                "eyr" => {
-                    expiration_year = match v.into().parse::<i32>() {
-                        Ok(num) => Some(num),
-                        Err(_) => None,
-                    }
-                }
-                "hgt" => {
-                    let height_string = v.into();
-                    let start_index = height_string.len() - 2;
-                    let unit = &height_string[start_index..];
-                    let size = &height_string[..start_index].to_string().parse::<i32>();
-                    height = match (unit, size) {
-                        ("cm", Ok(height)) => Height::CM(*height),
-                        ("in", Ok(height)) => Height::Inch(*height),
-                        _ => Height::Ivalid,
+        return None;
+    }
+    let mut scaled = integer.parse::<i64>().ok()?.checked_mul(factor)?;
+    if !fraction.is_empty() {
+        let denominator = 10_i64.pow(fraction.len() as u32);
+        let fraction = fraction.parse::<i64>().ok()?.checked_mul(factor)? / denominator;
+        scaled = scaled.checked_add(fraction)?;
+    }
+    Some(scaled)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldType {
+    Integer,
+    Text,
+    Height,
+}
+
+#[derive(Deserialize)]
+struct HeightRangeDefinition {
+    unit: String,
+    min: f64,
+    max: f64,
+}
+
+//The ranges of a field are unit-agnostic: a height in any unit is accepted if it falls into any
+//of them, so with 150-193cm and 59-76in a height of 193.04cm (76in) is valid. This keeps a
+//passport valid when its height is normalised to centimetres.
+struct HeightRange {
+    unit: HeightUnit,
+    min: f64,
+    max: f64,
+    micrometres: RangeInclusive<i64>,
+}
+
+impl HeightRange {
+    fn from(unit: HeightUnit, min: f64, max: f64) -> HeightRange {
+        let to_micrometres = |value: f64| (value * unit.micrometres() as f64).round() as i64;
+        HeightRange {
+            unit,
+            min,
+            max,
+            micrometres: to_micrometres(min)..=to_micrometres(max),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ConstraintDefinition {
+    Range { min: i64, max: i64 },
+    Pattern(String),
+    OneOf(Vec<String>),
+    Heights(Vec<HeightRangeDefinition>),
+}
+
+//Other keys like "comment" are ignored, so they can document a field in the schema file
+#[derive(Deserialize)]
+struct FieldDefinition {
+    key: String,
+    #[serde(default)]
+    required: bool,
+    #[serde(rename = "type")]
+    field_type: FieldType,
+    constraint: Option<ConstraintDefinition>,
+    normalize: Option<Normalization>,
+}
+
+//Applied when exporting passports, heights are always converted to centimetres
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Normalization {
+    LowerCase,
+    ZeroPad(usize),
+}
+
+#[derive(Deserialize)]
+struct SchemaDefinition {
+    fields: Vec<FieldDefinition>,
+}
+
+enum Constraint {
+    Range { min: i64, max: i64 },
+    Pattern(Arc<Regex>),
+    OneOf(Vec<String>),
+    Heights(Vec<HeightRange>),
+}
+
+impl Constraint {
+    fn from(
+        definition: ConstraintDefinition,
+        field: &FieldDefinition,
+    ) -> Result<Constraint, String> {
+        match (definition, field.field_type) {
+            (ConstraintDefinition::Range { min, max }, FieldType::Integer) => {
+                Ok(Constraint::Range { min, max })
+            }
+            (ConstraintDefinition::Pattern(pattern), _) => match pattern_cache::get(&pattern) {
+                Ok(regex) => Ok(Constraint::Pattern(regex)),
+                Err(e) => Err(format!("Invalid regular expression {}: {}", pattern, e)),
+            },
+            (ConstraintDefinition::OneOf(values), _) => Ok(Constraint::OneOf(values)),
+            (ConstraintDefinition::Heights(ranges), FieldType::Height) => {
+                let mut heights = vec![];
+                for range in ranges {
+                    match HeightUnit::from(&range.unit) {
+                        Some(unit) => heights.push(HeightRange::from(unit, range.min, range.max)),
+                        None => {
+                            return Err(format!(
+                                "Unknown height unit {} for field {}",
+                                range.unit, field.key
+                            ))
+                        }
                     }
                 }
-                "hcl" => hair_color = Some(v.into()),
-                "ecl" => eye_color = Some(v.into()),
-                "pid" => passport_id = Some(v.into()),
-                "cid" => country_id = Some(v.into()),
-                a => println!("Found unknown key {}", a),
-            };
+                Ok(Constraint::Heights(heights))
+            }
+            (_, field_type) => Err(format!(
+                "The constraint of field {} can not be used for {:?} values",
+                field.key, field_type
+            )),
         }
+    }
 
-        Passport {
-            birth_year,
-            issue_year,
-            expiration_year,
-            height,
-            hair_color,
-            eye_color,
-            passport_id,
-            country_id,
+    fn is_satisfied(&self, value: &str, typed_value: &FieldValue) -> bool {
+        match (self, typed_value) {
+            (Constraint::Range { min, max }, FieldValue::Integer(number)) => {
+                (*min..=*max).contains(number)
+            }
+            (Constraint::Pattern(regex), _) => regex.is_match(value),
+            (Constraint::OneOf(values), _) => values.iter().any(|v| v == value),
+            (Constraint::Heights(ranges), FieldValue::Height(height)) => {
+                //Unit-agnostic, see HeightRange
+                ranges
+                    .iter()
+                    .any(|range| range.micrometres.contains(&height.micrometres))
+            }
+            _ => false,
         }
     }
 
-    fn parse_passport_batch(input: &[String]) -> Passport {
-        let mut key_value_pairs: Vec<(&str, &str)> = vec![];
-        for line in input {
-            let k_v_combined = line.split_whitespace().collect::<Vec<_>>();
-            for k_v in k_v_combined {
-                let splitted = k_v.split(':').collect::<Vec<_>>();
-                if splitted.len() == 2 {
-                    key_value_pairs.push((splitted[0], splitted[1]));
+    fn to_violation(&self) -> Violation {
+        match self {
+            Constraint::Range { min, max } => Violation::OutOfRange {
+                min: *min,
+                max: *max,
+            },
+            Constraint::Pattern(regex) => Violation::PatternMismatch(regex.as_str().to_string()),
+            Constraint::OneOf(values) => Violation::NotOneOf(values.to_vec()),
+            Constraint::Heights(ranges) => Violation::HeightOutOfRange(
+                ranges
+                    .iter()
+                    .map(|range| format!("{}-{}{}", range.min, range.max, range.unit.symbol()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+enum FieldValue {
+    Integer(i64),
+    Text,
+    Height(Height),
+}
+
+impl FieldValue {
+    fn from(value: &str, field_type: FieldType) -> Option<FieldValue> {
+        match field_type {
+            FieldType::Integer => value.parse::<i64>().ok().map(FieldValue::Integer),
+            FieldType::Text => Some(FieldValue::Text),
+            FieldType::Height => Height::from(value).map(FieldValue::Height),
+        }
+    }
+}
+
+struct FieldRule {
+    key: String,
+    required: bool,
+    field_type: FieldType,
+    constraint: Option<Constraint>,
+    normalization: Option<Normalization>,
+}
+
+impl FieldRule {
+    //Values which can not be normalised are kept as they are
+    fn normalize(&self, value: &str) -> String {
+        let value = match self.field_type {
+            FieldType::Height => Height::from(value)
+                .map(|h| h.to_centimetres_string())
+                .unwrap_or_else(|| value.to_string()),
+            _ => value.to_string(),
+        };
+        match self.normalization {
+            Some(Normalization::LowerCase) => value.to_lowercase(),
+            Some(Normalization::ZeroPad(width))
+                if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) =>
+            {
+                format!("{:0>width$}", value, width = width)
+            }
+            _ => value,
+        }
+    }
+
+    fn validate(&self, value: Option<&str>) -> Option<Violation> {
+        let value = match value {
+            Some(v) => v,
+            None if self.required => return Some(Violation::Missing),
+            None => return None,
+        };
+        match (FieldValue::from(value, self.field_type), &self.constraint) {
+            (None, _) => Some(Violation::InvalidType(self.field_type)),
+            (Some(_), None) => None,
+            (Some(typed_value), Some(constraint)) => {
+                if constraint.is_satisfied(value, &typed_value) {
+                    None
                 } else {
-                    println!("Found a invalid key value pair: {}", k_v);
+                    Some(constraint.to_violation())
                 }
             }
         }
-        Passport::from(key_value_pairs)
     }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation {
+    Missing,
+    InvalidType(FieldType),
+    OutOfRange { min: i64, max: i64 },
+    PatternMismatch(String),
+    NotOneOf(Vec<String>),
+    HeightOutOfRange(Vec<String>),
+}
 
-    fn has_required_fields(&self) -> bool {
-        self.birth_year.is_some()
-            && self.issue_year.is_some()
-            && self.expiration_year.is_some()
-            && self.height != Height::NotFound
-            && self.birth_year.is_some()
-            && self.birth_year.is_some()
-            && self.birth_year.is_some()
-            && self.birth_year.is_some()
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Violation::Missing => write!(f, "is missing"),
+            Violation::InvalidType(field_type) => write!(f, "is not of type {:?}", field_type),
+            Violation::OutOfRange { min, max } => write!(f, "is not within {}-{}", min, max),
+            Violation::PatternMismatch(pattern) => write!(f, "does not match {}", pattern),
+            Violation::NotOneOf(values) => write!(f, "is not one of {}", values.join(", ")),
+            Violation::HeightOutOfRange(ranges) => {
+                write!(f, "is not within {}", ranges.join(" or "))
+            }
+        }
     }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldViolation {
+    pub key: String,
+    pub violation: Violation,
+    pub value: Option<String>,
+}
 
-    fn is_valid(&self) -> bool {
-        self.is_valid_birth_year()
-            && self.is_valid_issue_year()
-            && self.is_valid_expiration_year()
-            && self.is_valid_height()
-            && self.is_valid_hair_color()
-            && self.is_valid_eye_color()
-            && self.is_valid_passport_id()
-            && self.is_valid_country_id()
+impl fmt::Display for FieldViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.value {
+            Some(value) => write!(f, "{} {} ({})", self.key, self.violation, value),
+            None => write!(f, "{} {}", self.key, self.violation),
+        }
     }
+}
 
-    fn is_valid_birth_year(&self) -> bool {
-        match self.birth_year {
-            Some(year) => year >= 1920 && year <= 2002,
-            None => false,
+//How problems in the passport document itself (not its values) are handled
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Strictness {
+    #[default]
+    Lenient,
+    Warn,
+    Reject,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DocumentIssue {
+    UnknownKey(String),
+    DuplicateKey(String),
+    MalformedToken(String),
+}
+
+impl fmt::Display for DocumentIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DocumentIssue::UnknownKey(key) => write!(f, "unknown key {}", key),
+            DocumentIssue::DuplicateKey(key) => write!(f, "duplicate key {}", key),
+            DocumentIssue::MalformedToken(token) => write!(f, "malformed token {}", token),
         }
     }
-    fn is_valid_issue_year(&self) -> bool {
-        match self.issue_year {
-            Some(year) => year >= 2010 && year <= 2020,
-            None => false,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DocumentDecision {
+    //Either there were no issues or they were ignored because of lenient parsing
+    Accepted,
+    Warned,
+    Rejected,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ValidationReport {
+    pub violations: Vec<FieldViolation>,
+    pub issues: Vec<DocumentIssue>,
+    pub decision: DocumentDecision,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty() && self.decision != DocumentDecision::Rejected
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct ValidationSummary {
+    pub passports: usize,
+    pub valid: usize,
+    pub warned: usize,
+    pub rejected: usize,
+    //Number of passports failing per field and violated rule
+    pub failures: BTreeMap<(String, String), usize>,
+    //Number of occurrences per document issue, ignored issues are not counted
+    pub issues: BTreeMap<String, usize>,
+}
+
+impl ValidationSummary {
+    pub fn from(reports: &[ValidationReport]) -> ValidationSummary {
+        let mut summary = ValidationSummary {
+            passports: reports.len(),
+            ..Default::default()
+        };
+        for report in reports {
+            if report.is_valid() {
+                summary.valid += 1;
+            }
+            match report.decision {
+                DocumentDecision::Accepted => {}
+                DocumentDecision::Warned => summary.warned += 1,
+                DocumentDecision::Rejected => summary.rejected += 1,
+            }
+            if report.decision != DocumentDecision::Accepted {
+                for issue in report.issues.iter() {
+                    *summary.issues.entry(issue.to_string()).or_insert(0) += 1;
+                }
+            }
+            for violation in report.violations.iter() {
+                let reason = (violation.key.clone(), violation.violation.to_string());
+                *summary.failures.entry(reason).or_insert(0) += 1;
+            }
         }
+        summary
     }
-    fn is_valid_expiration_year(&self) -> bool {
-        match self.expiration_year {
-            Some(year) => year >= 2020 && year <= 2030,
-            None => false,
+}
+
+impl fmt::Display for ValidationSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} of {} passports are valid",
+            self.valid, self.passports
+        )?;
+        for ((key, reason), count) in self.failures.iter() {
+            writeln!(f, "{:>5} {} {}", count, key, reason)?;
+        }
+        if self.warned > 0 {
+            writeln!(f, "{} passports have warnings", self.warned)?;
+        }
+        if self.rejected > 0 {
+            writeln!(f, "{} passports were rejected", self.rejected)?;
         }
+        for (issue, count) in self.issues.iter() {
+            writeln!(f, "{:>5} {}", count, issue)?;
+        }
+        Ok(())
     }
-    fn is_valid_height(&self) -> bool {
-        match &self.height {
-            Height::CM(size) => *size >= 150 && *size <= 193,
-            Height::Inch(size) => *size >= 59 && *size <= 76,
-            _ => false,
+}
+
+pub struct PassportSchema {
+    fields: Vec<FieldRule>,
+}
+
+impl PassportSchema {
+    pub fn north_pole() -> PassportSchema {
+        PassportSchema::from_json(NORTH_POLE_SCHEMA).expect("The North Pole schema is invalid")
+    }
+
+    pub fn from_file(filename: &str) -> Result<PassportSchema, String> {
+        match fs::read_to_string(filename) {
+            Ok(input) => PassportSchema::from_json(&input),
+            Err(_) => Err(format!("Failed to open the file {}", filename)),
         }
     }
-    fn is_valid_hair_color(&self) -> bool {
-        let hair_regex = Regex::new("#[0-9 a-f]{6}$").expect("Invalid regular expression");
-        match &self.hair_color {
-            Some(color) => hair_regex.is_match(color.as_str()),
-            None => false,
+
+    pub fn from_json(input: &str) -> Result<PassportSchema, String> {
+        let definition: SchemaDefinition = match serde_json::from_str(input) {
+            Ok(d) => d,
+            Err(e) => return Err(format!("Failed to parse the schema: {}", e)),
+        };
+
+        let mut fields: Vec<FieldRule> = vec![];
+        for mut field in definition.fields {
+            if fields.iter().any(|f| f.key == field.key) {
+                return Err(format!("The field {} is defined twice", field.key));
+            }
+            let constraint = match field.constraint.take() {
+                Some(c) => Some(Constraint::from(c, &field)?),
+                None => None,
+            };
+            fields.push(FieldRule {
+                key: field.key,
+                required: field.required,
+                field_type: field.field_type,
+                constraint,
+                normalization: field.normalize,
+            });
+        }
+        Ok(PassportSchema { fields })
+    }
+
+    pub fn has_required_fields(&self, passport: &Passport) -> bool {
+        self.fields
+            .iter()
+            .filter(|f| f.required)
+            .all(|f| passport.get(&f.key).is_some())
+    }
+
+    pub fn is_valid(&self, passport: &Passport) -> bool {
+        self.validate(passport).is_valid()
+    }
+
+    pub fn validate(&self, passport: &Passport) -> ValidationReport {
+        let violations = self
+            .fields
+            .iter()
+            .filter_map(|f| {
+                let value = passport.get(&f.key);
+                f.validate(value).map(|violation| FieldViolation {
+                    key: f.key.to_string(),
+                    violation,
+                    value: value.map(|v| v.to_string()),
+                })
+            })
+            .collect();
+
+        let issues = self.document_issues(passport);
+        let decision = match passport.strictness {
+            _ if issues.is_empty() => DocumentDecision::Accepted,
+            Strictness::Lenient => DocumentDecision::Accepted,
+            Strictness::Warn => DocumentDecision::Warned,
+            Strictness::Reject => DocumentDecision::Rejected,
+        };
+        ValidationReport {
+            violations,
+            issues,
+            decision,
         }
     }
-    fn is_valid_eye_color(&self) -> bool {
-        let valid_colors = ["amb", "blu", "brn", "gry", "grn", "hzl", "oth"];
-        match &self.eye_color {
-            Some(color) => valid_colors.contains(&color.as_str()),
-            None => false,
+
+    //Schema fields come first in schema order, unknown fields follow in their original order.
+    //Duplicate keys are resolved and malformed tokens are dropped.
+    pub fn canonicalize(&self, passport: &Passport) -> Passport {
+        let mut fields = vec![];
+        for rule in self.fields.iter() {
+            if let Some(value) = passport.get(&rule.key) {
+                fields.push((rule.key.to_string(), rule.normalize(value)));
+            }
+        }
+        for (key, _) in passport.fields.iter() {
+            if !fields.iter().any(|(k, _)| k == key) {
+                fields.push((key.to_string(), passport.get(key).unwrap().to_string()));
+            }
+        }
+        Passport {
+            fields,
+            malformed_tokens: vec![],
+            strictness: passport.strictness,
         }
     }
-    fn is_valid_passport_id(&self) -> bool {
-        match &self.passport_id {
-            Some(id) => {
-                if id.len() != 9 {
-                    return false;
-                }
-                for c in id.chars() {
-                    if !c.is_digit(10) {
-                        return false;
-                    }
+
+    //The batch format of the puzzle input with one passport per line
+    pub fn export_batch(&self, passports: &[Passport]) -> String {
+        passports
+            .iter()
+            .map(|p| format!("{}\n", self.canonicalize(p)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn export_json(&self, passports: &[Passport]) -> Result<String, String> {
+        let passports = passports
+            .iter()
+            .map(|p| self.canonicalize(p))
+            .collect::<Vec<_>>();
+        serde_json::to_string_pretty(&passports)
+            .map_err(|e| format!("Failed to export the passports: {}", e))
+    }
+
+    //One column per schema field, unknown fields are not exported
+    pub fn export_csv(&self, passports: &[Passport]) -> Result<String, String> {
+        export::to_csv(|writer| {
+            writer.write_record(self.fields.iter().map(|f| f.key.as_str()))?;
+            for passport in passports {
+                let passport = self.canonicalize(passport);
+                writer.write_record(
+                    self.fields
+                        .iter()
+                        .map(|f| passport.get(&f.key).unwrap_or_default()),
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    fn document_issues(&self, passport: &Passport) -> Vec<DocumentIssue> {
+        let mut issues = vec![];
+        for (index, (key, _)) in passport.fields.iter().enumerate() {
+            //Every key is only reported once, at its first occurrence
+            if passport.fields[..index].iter().any(|(k, _)| k == key) {
+                continue;
+            }
+            if !self.fields.iter().any(|f| &f.key == key) {
+                issues.push(DocumentIssue::UnknownKey(key.to_string()));
+            }
+            if passport.fields[index + 1..].iter().any(|(k, _)| k == key) {
+                issues.push(DocumentIssue::DuplicateKey(key.to_string()));
+            }
+        }
+        issues.extend(
+            passport
+                .malformed_tokens
+                .iter()
+                .map(|t| DocumentIssue::MalformedToken(t.to_string())),
+        );
+        issues
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Passport {
+    fields: Vec<(String, String)>,
+    malformed_tokens: Vec<String>,
+    strictness: Strictness,
+}
+
+impl Passport {
+    pub fn from<T>(input: Vec<(T, T)>) -> Passport
+    where
+        T: Into<String>,
+    {
+        Passport {
+            fields: input
+                .into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+            malformed_tokens: vec![],
+            strictness: Strictness::Lenient,
+        }
+    }
+
+    pub fn parse_passport_batch(input: &[String]) -> Passport {
+        Passport::parse_passport_batch_with(input, Strictness::Lenient)
+    }
+
+    //Unknown and duplicate keys as well as malformed tokens are reported by the validation
+    pub fn parse_passport_batch_with(input: &[String], strictness: Strictness) -> Passport {
+        let mut key_value_pairs: Vec<(&str, &str)> = vec![];
+        let mut malformed_tokens = vec![];
+        for line in input {
+            let k_v_combined = line.split_whitespace().collect::<Vec<_>>();
+            for k_v in k_v_combined {
+                let splitted = k_v.split(':').collect::<Vec<_>>();
+                if splitted.len() == 2 {
+                    key_value_pairs.push((splitted[0], splitted[1]));
+                } else {
+                    malformed_tokens.push(k_v.to_string());
                 }
-                true
             }
-            None => false,
+        }
+        Passport {
+            malformed_tokens,
+            strictness,
+            ..Passport::from(key_value_pairs)
         }
     }
-    fn is_valid_country_id(&self) -> bool {
-        match self.country_id {
-            Some(_) => true,
-            None => true,
+
+    //Later values of a key overwrite earlier ones
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+impl fmt::Display for Passport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fields = self
+            .fields
+            .iter()
+            .map(|(k, v)| format!("{}:{}", k, v))
+            .collect::<Vec<_>>();
+        write!(f, "{}", fields.join(" "))
+    }
+}
+
+impl Serialize for Passport {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.fields.len()))?;
+        for (key, value) in self.fields.iter() {
+            map.serialize_entry(key, value)?;
         }
+        map.end()
     }
 }
 
 pub fn part1() {
     let input_file = load_input::load_empty_line_seperated("./resources/Day4Input.txt")
         .expect("Failed to read the input");
+    let schema = PassportSchema::north_pole();
 
     let ports = input_file
         .iter()
         .map(|batch| Passport::parse_passport_batch(batch))
         .collect::<Vec<_>>();
     let valid_ports = ports
-        .into_iter()
-        .filter(|p| p.has_required_fields())
+        .iter()
+        .filter(|p| schema.has_required_fields(p))
         .count();
     println!("{} passports have all required fields", valid_ports);
 }
@@ -204,13 +777,29 @@ pub fn part1() {
 pub fn part2() {
     let input_file = load_input::load_empty_line_seperated("./resources/Day4Input.txt")
         .expect("Failed to read the input");
+    let schema = PassportSchema::north_pole();
+
+    let ports = input_file
+        .iter()
+        .map(|batch| Passport::parse_passport_batch_with(batch, Strictness::Warn))
+        .collect::<Vec<_>>();
+    let reports = ports.iter().map(|p| schema.validate(p)).collect::<Vec<_>>();
+    print!("{}", ValidationSummary::from(&reports));
+}
+
+//Writes the passports with normalised fields for other tools
+pub fn export() {
+    let input_file = load_input::load_empty_line_seperated("./resources/Day4Input.txt")
+        .expect("Failed to read the input");
+    let schema = PassportSchema::north_pole();
 
     let ports = input_file
         .iter()
         .map(|batch| Passport::parse_passport_batch(batch))
         .collect::<Vec<_>>();
-    let valid_ports = ports.into_iter().filter(|p| p.is_valid()).count();
-    println!("{} passports are valid", valid_ports);
+    export::write_file("./passports.txt", Ok(schema.export_batch(&ports)));
+    export::write_file("./passports.json", schema.export_json(&ports));
+    export::write_file("./passports.csv", schema.export_csv(&ports));
 }
 
 #[cfg(test)]
@@ -242,29 +831,32 @@ mod tests {
             "iyr:2011 ecl:brn hgt:59in".to_string(),
         ];
 
-        let input = vec![input_1, input_2, input_3, input_4, input_5];
+        let input = [input_1, input_2, input_3, input_4, input_5];
+        let schema = PassportSchema::north_pole();
         let ports = input
             .iter()
             .map(|batch| Passport::parse_passport_batch(batch))
             .collect::<Vec<_>>();
 
+        //Only input_1 has every required field, input_3 and input_4 are each half a passport
         assert_eq!(
-            2,
+            1,
             ports
-                .into_iter()
-                .filter(|p| p.has_required_fields())
+                .iter()
+                .filter(|p| schema.has_required_fields(p))
                 .count()
         );
     }
 
     #[test]
     fn test_validate_passport() {
+        let schema = PassportSchema::north_pole();
         let input = [
             "eyr:1972 cid:100".to_string(),
             "hcl:#18171d ecl:amb hgt:170 pid:186cm iyr:2018 byr:1926".to_string(),
         ];
         let port = Passport::parse_passport_batch(&input);
-        assert!(!port.is_valid());
+        assert!(!schema.is_valid(&port));
 
         let input = [
             "iyr:2019".to_string(),
@@ -272,14 +864,14 @@ mod tests {
             "ecl:grn pid:012533040 byr:1946".to_string(),
         ];
         let port = Passport::parse_passport_batch(&input);
-        assert!(!port.is_valid());
+        assert!(!schema.is_valid(&port));
 
         let input = [
             "hcl:dab227 iyr:2012".to_string(),
             "ecl:brn hgt:182cm pid:021572410 eyr:2020 byr:1992 cid:277".to_string(),
         ];
         let port = Passport::parse_passport_batch(&input);
-        assert!(!port.is_valid());
+        assert!(!schema.is_valid(&port));
 
         let input = [
             "hgt:59cm ecl:zzz".to_string(),
@@ -288,14 +880,14 @@ mod tests {
         ];
 
         let port = Passport::parse_passport_batch(&input);
-        assert!(!port.is_valid());
+        assert!(!schema.is_valid(&port));
 
         let input = [
             "pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980".to_string(),
             "hcl:#623a2f".to_string(),
         ];
         let port = Passport::parse_passport_batch(&input);
-        let is_valid = port.is_valid();
+        let is_valid = schema.is_valid(&port);
         assert!(is_valid);
 
         let input = [
@@ -303,7 +895,7 @@ mod tests {
             "iyr:2014 pid:896056539 hcl:#a97842 hgt:165cm".to_string(),
         ];
         let port = Passport::parse_passport_batch(&input);
-        let is_valid = port.is_valid();
+        let is_valid = schema.is_valid(&port);
         assert!(is_valid);
 
         let input = [
@@ -313,14 +905,437 @@ mod tests {
             "eyr:2022".to_string(),
         ];
         let port = Passport::parse_passport_batch(&input);
-        let is_valid = port.is_valid();
+        let is_valid = schema.is_valid(&port);
         assert!(is_valid);
 
         let input = [
             "iyr:2010 hgt:158cm hcl:#b6652a ecl:blu byr:1944 eyr:2021 pid:093154719".to_string(),
         ];
         let port = Passport::parse_passport_batch(&input);
-        let is_valid = port.is_valid();
+        let is_valid = schema.is_valid(&port);
         assert!(is_valid);
     }
+
+    #[test]
+    fn test_validation_report() {
+        let schema = PassportSchema::north_pole();
+        let input = [
+            "eyr:1972 cid:100".to_string(),
+            "hcl:#18171d ecl:amb hgt:170 pid:186cm iyr:2018 byr:1926".to_string(),
+        ];
+        let report = schema.validate(&Passport::parse_passport_batch(&input));
+        assert!(!report.is_valid());
+        assert_eq!(
+            vec![
+                FieldViolation {
+                    key: "eyr".to_string(),
+                    violation: Violation::OutOfRange {
+                        min: 2020,
+                        max: 2030
+                    },
+                    value: Some("1972".to_string()),
+                },
+                FieldViolation {
+                    key: "hgt".to_string(),
+                    violation: Violation::InvalidType(FieldType::Height),
+                    value: Some("170".to_string()),
+                },
+                FieldViolation {
+                    key: "pid".to_string(),
+                    violation: Violation::PatternMismatch("^[0-9]{9}$".to_string()),
+                    value: Some("186cm".to_string()),
+                },
+            ],
+            report.violations
+        );
+        assert_eq!(
+            "eyr is not within 2020-2030 (1972)",
+            report.violations[0].to_string()
+        );
+
+        let input = ["hgt:59cm ecl:zzz".to_string()];
+        let report = schema.validate(&Passport::parse_passport_batch(&input));
+        let failures = report
+            .violations
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                "byr is missing",
+                "iyr is missing",
+                "eyr is missing",
+                "hgt is not within 150-193cm or 59-76in (59cm)",
+                "hcl is missing",
+                "ecl is not one of amb, blu, brn, gry, grn, hzl, oth (zzz)",
+                "pid is missing",
+            ],
+            failures
+        );
+    }
+
+    #[test]
+    fn test_validation_summary() {
+        let schema = PassportSchema::north_pole();
+        let reports = [
+            "eyr:1972 cid:100 hcl:#18171d ecl:amb hgt:170 pid:186cm iyr:2018 byr:1926",
+            "iyr:2019 hcl:#602927 eyr:1967 hgt:170cm ecl:grn pid:012533040 byr:1946",
+            "iyr:2010 hgt:158cm hcl:#b6652a ecl:blu byr:1944 eyr:2021 pid:093154719",
+        ]
+        .iter()
+        .map(|l| schema.validate(&Passport::parse_passport_batch(&[l.to_string()])))
+        .collect::<Vec<_>>();
+
+        let summary = ValidationSummary::from(&reports);
+        assert_eq!(3, summary.passports);
+        assert_eq!(1, summary.valid);
+        assert_eq!(3, summary.failures.len());
+        assert_eq!(
+            Some(&2),
+            summary
+                .failures
+                .get(&("eyr".to_string(), "is not within 2020-2030".to_string()))
+        );
+        assert_eq!(
+            "1 of 3 passports are valid\n    2 eyr is not within 2020-2030\n    1 hgt is not of type Height\n    1 pid does not match ^[0-9]{9}$\n",
+            summary.to_string()
+        );
+    }
+
+    #[test]
+    fn test_required_fields() {
+        let schema = PassportSchema::north_pole();
+        let complete =
+            ["pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980 hcl:#623a2f".to_string()];
+        assert!(schema.has_required_fields(&Passport::parse_passport_batch(&complete)));
+
+        for missing in ["hgt", "hcl", "ecl", "pid"].iter() {
+            let input = complete[0]
+                .split_whitespace()
+                .filter(|k_v| !k_v.starts_with(missing))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let port = Passport::parse_passport_batch(&[input]);
+            assert!(
+                !schema.has_required_fields(&port),
+                "{} is required",
+                missing
+            );
+        }
+    }
+
+    #[test]
+    fn test_custom_schema() {
+        let schema = PassportSchema::from_json(
+            r#"{
+                "fields": [
+                    { "key": "name", "required": true, "type": "text", "constraint": { "pattern": "^[A-Z][a-z]+$" } },
+                    { "key": "age", "required": true, "type": "integer", "constraint": { "range": { "min": 18, "max": 99 } } },
+                    { "key": "role", "type": "text", "constraint": { "one_of": ["elf", "reindeer"] } }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let port =
+            Passport::parse_passport_batch(&["name:Rudolph age:42 role:reindeer".to_string()]);
+        assert!(schema.is_valid(&port));
+        let port = Passport::parse_passport_batch(&["name:Buddy age:30".to_string()]);
+        assert!(schema.is_valid(&port));
+        let port = Passport::parse_passport_batch(&["name:Buddy age:30 role:human".to_string()]);
+        assert!(!schema.is_valid(&port));
+        let port = Passport::parse_passport_batch(&["name:buddy age:30".to_string()]);
+        assert!(!schema.is_valid(&port));
+        let port = Passport::parse_passport_batch(&["name:Buddy age:thirty".to_string()]);
+        assert!(!schema.is_valid(&port));
+        let port = Passport::parse_passport_batch(&["name:Buddy".to_string()]);
+        assert!(!schema.has_required_fields(&port));
+    }
+
+    #[test]
+    fn test_invalid_schema() {
+        assert!(PassportSchema::from_json("{").is_err());
+        assert!(PassportSchema::from_json(
+            r#"{ "fields": [ { "key": "a", "type": "text", "constraint": { "range": { "min": 1, "max": 2 } } } ] }"#
+        )
+        .is_err());
+        assert!(PassportSchema::from_json(
+            r#"{ "fields": [ { "key": "a", "type": "text", "constraint": { "pattern": "(" } } ] }"#
+        )
+        .is_err());
+        assert!(PassportSchema::from_json(
+            r#"{ "fields": [ { "key": "a", "type": "height", "constraint": { "heights": [ { "unit": "yd", "min": 1, "max": 2 } ] } } ] }"#
+        )
+        .is_err());
+        assert!(PassportSchema::from_json(
+            r#"{ "fields": [ { "key": "a", "type": "text" }, { "key": "a", "type": "integer" } ] }"#
+        )
+        .is_err());
+        assert!(
+            PassportSchema::from_json(r#"{ "fields": [ { "key": "a", "type": "date" } ] }"#)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_height() {
+        let height = Height::from("183cm").unwrap();
+        assert_eq!(1_830_000, height.micrometres());
+        assert_eq!(HeightUnit::Centimeter, height.unit);
+        assert_eq!("183cm", height.to_string());
+
+        assert_eq!(
+            Some(1_830_000),
+            Height::from("1830mm").map(|h| h.micrometres())
+        );
+        assert_eq!(
+            Some(1_830_000),
+            Height::from("1.83m").map(|h| h.micrometres())
+        );
+        assert_eq!(
+            Some(1_828_800),
+            Height::from("72in").map(|h| h.micrometres())
+        );
+        assert_eq!(
+            Some(1_828_800),
+            Height::from("6ft").map(|h| h.micrometres())
+        );
+        assert_eq!(Some(1_828_800), Height::from("6'").map(|h| h.micrometres()));
+        assert_eq!(
+            Some(1_803_400),
+            Height::from("5'11\"").map(|h| h.micrometres())
+        );
+        assert_eq!(
+            Some(1_816_100),
+            Height::from("5'11.5").map(|h| h.micrometres())
+        );
+        assert_eq!(HeightUnit::Foot, Height::from("5'11\"").unwrap().unit);
+        assert!(
+            (Height::from("72in")
+                .unwrap()
+                .in_unit(HeightUnit::Centimeter)
+                - 182.88)
+                .abs()
+                < 1e-9
+        );
+
+        for invalid in [
+            "",
+            "c",
+            "cm",
+            "1",
+            "170",
+            "1.cm",
+            ".5m",
+            "1.2.3m",
+            "-5cm",
+            "5yd",
+            "5'12\"",
+            "'5",
+            "5'a",
+            "99999999999999999999cm",
+            "1.1234567m",
+        ] {
+            assert_eq!(None, Height::from(invalid), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_validate_height_units() {
+        let schema = PassportSchema::north_pole();
+        let validate = |height: &str| {
+            schema
+                .fields
+                .iter()
+                .find(|f| f.key == "hgt")
+                .unwrap()
+                .validate(Some(height))
+        };
+        for valid in [
+            "150cm", "193cm", "59in", "76in", "1.6m", "1700mm", "5'11\"", "6'",
+        ] {
+            assert_eq!(None, validate(valid), "{}", valid);
+        }
+        assert_eq!(
+            Some(Violation::HeightOutOfRange(vec![
+                "150-193cm".to_string(),
+                "59-76in".to_string()
+            ])),
+            validate("4'10\"")
+        );
+        assert_eq!(
+            Some(Violation::InvalidType(FieldType::Height)),
+            validate("1.m")
+        );
+
+        let schema = PassportSchema::from_json(
+            r#"{ "fields": [ { "key": "hgt", "type": "height", "constraint": { "heights": [ { "unit": "m", "min": 1.5, "max": 1.93 } ] } } ] }"#,
+        )
+        .unwrap();
+        let passport = Passport::from(vec![("hgt", "6'4\"")]);
+        assert_eq!(
+            "hgt is not within 1.5-1.93m (6'4\")",
+            schema.validate(&passport).violations[0].to_string()
+        );
+        assert!(schema.is_valid(&Passport::from(vec![("hgt", "6'3\"")])));
+    }
+
+    #[test]
+    fn test_height_ranges_are_unit_agnostic() {
+        let schema = PassportSchema::north_pole();
+        let is_valid = |height: &str| {
+            schema
+                .fields
+                .iter()
+                .find(|f| f.key == "hgt")
+                .unwrap()
+                .validate(Some(height))
+                .is_none()
+        };
+        //59in is 149.86cm and 76in is 193.04cm, so the inch range widens the cm range
+        assert!(is_valid("149.86cm"));
+        assert!(!is_valid("149.85cm"));
+        assert!(is_valid("193.04cm"));
+        assert!(!is_valid("193.05cm"));
+        assert!(is_valid("1930.4mm"));
+        //150cm is 59.06in and 193cm is 75.98in, so the cm range adds nothing to the inch range
+        assert!(is_valid("59in"));
+        assert!(!is_valid("58.99in"));
+        assert!(is_valid("76in"));
+        assert!(!is_valid("76.01in"));
+        assert!(is_valid("6'4\""));
+    }
+
+    #[test]
+    fn test_strictness() {
+        let schema = PassportSchema::north_pole();
+        let input = [
+            "pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980".to_string(),
+            "hcl:#623a2f byr:1981 nick:rudolph cid:12:3 shoesize".to_string(),
+        ];
+        let expected_issues = vec![
+            DocumentIssue::DuplicateKey("byr".to_string()),
+            DocumentIssue::UnknownKey("nick".to_string()),
+            DocumentIssue::MalformedToken("cid:12:3".to_string()),
+            DocumentIssue::MalformedToken("shoesize".to_string()),
+        ];
+
+        let lenient = schema.validate(&Passport::parse_passport_batch(&input));
+        assert_eq!(expected_issues, lenient.issues);
+        assert_eq!(DocumentDecision::Accepted, lenient.decision);
+        assert!(lenient.is_valid());
+
+        let warn = schema.validate(&Passport::parse_passport_batch_with(
+            &input,
+            Strictness::Warn,
+        ));
+        assert_eq!(expected_issues, warn.issues);
+        assert_eq!(DocumentDecision::Warned, warn.decision);
+        assert!(warn.is_valid());
+
+        let reject = schema.validate(&Passport::parse_passport_batch_with(
+            &input,
+            Strictness::Reject,
+        ));
+        assert_eq!(DocumentDecision::Rejected, reject.decision);
+        assert!(!reject.is_valid());
+        assert!(reject.violations.is_empty());
+
+        let clean = schema.validate(&Passport::parse_passport_batch_with(
+            &input[..1],
+            Strictness::Reject,
+        ));
+        assert!(clean.issues.is_empty());
+        assert_eq!(DocumentDecision::Accepted, clean.decision);
+
+        let summary = ValidationSummary::from(&[lenient, warn, reject]);
+        assert_eq!(2, summary.valid);
+        assert_eq!(1, summary.warned);
+        assert_eq!(1, summary.rejected);
+        assert_eq!(Some(&2), summary.issues.get("unknown key nick"));
+        assert!(summary
+            .to_string()
+            .ends_with("1 passports have warnings\n1 passports were rejected\n    2 duplicate key byr\n    2 malformed token cid:12:3\n    2 malformed token shoesize\n    2 unknown key nick\n"));
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let schema = PassportSchema::north_pole();
+        let input = [
+            "cid:88 hgt:74in pid:87499704 nick:rudolph".to_string(),
+            "hcl:#623A2F ecl:GRN byr:1980 byr:1981 broken".to_string(),
+        ];
+        let passport = schema.canonicalize(&Passport::parse_passport_batch(&input));
+        assert_eq!(
+            "byr:1981 hgt:187.96cm hcl:#623a2f ecl:grn pid:087499704 cid:88 nick:rudolph",
+            passport.to_string()
+        );
+        assert_eq!(Some("087499704"), passport.get("pid"));
+
+        let passport = Passport::from(vec![("hgt", "1.6m"), ("pid", "12a"), ("ecl", "Blu")]);
+        assert_eq!(
+            "hgt:160cm ecl:blu pid:12a",
+            schema.canonicalize(&passport).to_string()
+        );
+        let passport = Passport::from(vec![("hgt", "5'11\""), ("hcl", "tall")]);
+        assert_eq!(
+            "hgt:180.34cm hcl:tall",
+            schema.canonicalize(&passport).to_string()
+        );
+        let passport = Passport::from(vec![("hgt", "190")]);
+        assert_eq!("hgt:190", schema.canonicalize(&passport).to_string());
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let schema = PassportSchema::north_pole();
+        let input_file = load_input::load_empty_line_seperated("./resources/Day4Input.txt")
+            .expect("Failed to read the input");
+        let ports = input_file
+            .iter()
+            .map(|batch| Passport::parse_passport_batch(batch))
+            .collect::<Vec<_>>();
+
+        let exported = schema.export_batch(&ports);
+        let reimported = exported
+            .split("\n\n")
+            .map(|batch| {
+                let lines = batch.lines().map(|l| l.to_string()).collect::<Vec<_>>();
+                Passport::parse_passport_batch(&lines)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(ports.len(), reimported.len());
+        assert_eq!(exported, schema.export_batch(&reimported));
+        for (port, reimported) in ports.iter().zip(reimported.iter()) {
+            assert_eq!(schema.canonicalize(port), *reimported);
+            //Normalising can repair a value, e.g. a short pid, but never break a valid one
+            assert!(!schema.is_valid(port) || schema.is_valid(reimported));
+        }
+    }
+
+    #[test]
+    fn test_export_json_and_csv() {
+        let schema = PassportSchema::north_pole();
+        let ports = vec![
+            Passport::from(vec![("pid", "1"), ("hgt", "60in"), ("ecl", "AMB")]),
+            Passport::from(vec![("hcl", "#ABCDEF"), ("cid", "a,b")]),
+        ];
+
+        let json = schema.export_json(&ports).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            serde_json::json!([
+                { "hgt": "152.4cm", "ecl": "amb", "pid": "000000001" },
+                { "hcl": "#abcdef", "cid": "a,b" }
+            ]),
+            parsed
+        );
+        //Keys keep the canonical order
+        assert!(json.find("hgt").unwrap() < json.find("ecl").unwrap());
+
+        assert_eq!(
+            "byr,iyr,eyr,hgt,hcl,ecl,pid,cid\n,,,152.4cm,,amb,000000001,\n,,,,#abcdef,,,\"a,b\"\n",
+            schema.export_csv(&ports).unwrap()
+        );
+    }
 }
@@ -1,58 +1,180 @@
-use regex::Regex;
+use std::fmt;
+
+use unicode_segmentation::UnicodeSegmentation;
 
 use crate::load_input;
 
-struct PasswordWithPolicy {
-    password: String,
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PositionUnit {
+    Char,
+    Byte,
+    Grapheme,
+}
+
+impl PositionUnit {
+    fn from(name: &str) -> Option<PositionUnit> {
+        match name {
+            "char" => Some(PositionUnit::Char),
+            "byte" => Some(PositionUnit::Byte),
+            "grapheme" => Some(PositionUnit::Grapheme),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum PolicyError {
+    InvalidFormat(String),
+    ZeroPosition,
+    ReversedRange(usize, usize),
+    UnknownUnit(String),
+    UnsupportedCheckCharacter(char, PositionUnit),
+}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PolicyError::InvalidFormat(input) => write!(f, "Invalid policy format: {}", input),
+            PolicyError::ZeroPosition => write!(f, "Policy positions start at 1"),
+            PolicyError::ReversedRange(lower, upper) => {
+                write!(f, "Policy range {}-{} is reversed", lower, upper)
+            }
+            PolicyError::UnknownUnit(unit) => write!(
+                f,
+                "Unknown position unit {}, expected char, byte or grapheme",
+                unit
+            ),
+            PolicyError::UnsupportedCheckCharacter(c, unit) => {
+                write!(
+                    f,
+                    "Check character {} can not be used with {:?} positions",
+                    c, unit
+                )
+            }
+        }
+    }
+}
+
+struct PasswordWithPolicy<'a> {
+    password: &'a str,
     check_character: char,
     lower: usize,
     upper: usize,
+    unit: PositionUnit,
 }
 
-impl PasswordWithPolicy {
-    fn from(input: &str) -> Option<PasswordWithPolicy> {
-        let input_regex =
-            Regex::new("(\\d+)-(\\d+) (.): (.*)").expect("Invalid regular expression");
-        if input_regex.is_match(&input) {
-            let groups = input_regex.captures_iter(&input).next().unwrap();
-            Some(PasswordWithPolicy {
-                password: groups[4].to_string(),
-                check_character: groups[3]
-                    .chars()
-                    .next()
-                    .expect("Failed to parse the test char"),
-                upper: groups[2]
-                    .parse::<usize>()
-                    .expect("Failed to parse upper range"),
-                lower: groups[1]
-                    .parse::<usize>()
-                    .expect("Failed to parse lower range"),
-            })
-        } else {
-            None
+impl<'a> PasswordWithPolicy<'a> {
+    fn from(input: &'a str) -> Result<PasswordWithPolicy<'a>, PolicyError> {
+        PasswordWithPolicy::from_with_unit(input, PositionUnit::Char)
+    }
+
+    //Parses "<lower>-<upper>[/<unit>] <char>: <password>" without allocating, the password borrows
+    //from the input. The unit suffix of the policy overrides the given default unit.
+    fn from_with_unit(
+        input: &'a str,
+        unit: PositionUnit,
+    ) -> Result<PasswordWithPolicy<'a>, PolicyError> {
+        let invalid_format = || PolicyError::InvalidFormat(input.to_string());
+        let (range, policy) = input.split_once(' ').ok_or_else(invalid_format)?;
+        let (range, unit) = match range.split_once('/') {
+            Some((range, name)) => (
+                range,
+                PositionUnit::from(name)
+                    .ok_or_else(|| PolicyError::UnknownUnit(name.to_string()))?,
+            ),
+            None => (range, unit),
+        };
+        let (lower, upper) = range.split_once('-').ok_or_else(invalid_format)?;
+        let mut policy_chars = policy.chars();
+        let check_character = policy_chars.next().ok_or_else(invalid_format)?;
+        let password = policy_chars
+            .as_str()
+            .strip_prefix(": ")
+            .ok_or_else(invalid_format)?;
+        let lower = parse_position(lower).ok_or_else(invalid_format)?;
+        let upper = parse_position(upper).ok_or_else(invalid_format)?;
+
+        if lower == 0 || upper == 0 {
+            return Err(PolicyError::ZeroPosition);
+        }
+        if lower > upper {
+            return Err(PolicyError::ReversedRange(lower, upper));
+        }
+        if unit == PositionUnit::Byte && !check_character.is_ascii() {
+            return Err(PolicyError::UnsupportedCheckCharacter(
+                check_character,
+                unit,
+            ));
         }
+
+        Ok(PasswordWithPolicy {
+            password,
+            check_character,
+            lower,
+            upper,
+            unit,
+        })
     }
 
     fn is_valid_sled_rental(&self) -> bool {
-        let mut count: usize = 0;
-        for c in self.password.chars() {
-            if self.check_character == c {
-                count += 1;
+        let count = match self.unit {
+            PositionUnit::Char => self
+                .password
+                .chars()
+                .filter(|c| *c == self.check_character)
+                .count(),
+            PositionUnit::Byte => self
+                .password
+                .bytes()
+                .filter(|b| *b as char == self.check_character)
+                .count(),
+            PositionUnit::Grapheme => {
+                let mut buffer = [0; 4];
+                let check = self.check_character.encode_utf8(&mut buffer);
+                self.password
+                    .graphemes(true)
+                    .filter(|g| *g == check)
+                    .count()
             }
-        }
+        };
         self.lower <= count && self.upper >= count
     }
 
     fn is_valid_toboggan_corporate(&self) -> bool {
-        let chars = self.password.chars().collect::<Vec<char>>();
-        match (chars.get(self.lower - 1), chars.get(self.upper - 1)) {
-            (Some(char_1), Some(char_2)) => {
-                (char_1 == &self.check_character && char_2 != &self.check_character)
-                    || (char_1 != &self.check_character && char_2 == &self.check_character)
-            }
+        match (self.matches_at(self.lower), self.matches_at(self.upper)) {
+            (Some(match_1), Some(match_2)) => match_1 != match_2,
             _ => false,
         }
     }
+
+    //Returns None if the 1-based position is outside of the password
+    fn matches_at(&self, position: usize) -> Option<bool> {
+        let index = position - 1;
+        match self.unit {
+            PositionUnit::Char => self
+                .password
+                .chars()
+                .nth(index)
+                .map(|c| c == self.check_character),
+            PositionUnit::Byte => self
+                .password
+                .as_bytes()
+                .get(index)
+                .map(|b| *b as char == self.check_character),
+            PositionUnit::Grapheme => {
+                let mut buffer = [0; 4];
+                let check = self.check_character.encode_utf8(&mut buffer);
+                self.password.graphemes(true).nth(index).map(|g| g == check)
+            }
+        }
+    }
+}
+
+fn parse_position(input: &str) -> Option<usize> {
+    if input.is_empty() || !input.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    input.parse::<usize>().ok()
 }
 
 pub fn part1() {
@@ -62,7 +184,7 @@ pub fn part1() {
     println!("Found {} passwords", input_file.len());
 
     let count = input_file
-        .into_iter()
+        .iter()
         .filter(|s| {
             PasswordWithPolicy::from(s)
                 .expect("Invalid Password")
@@ -79,45 +201,250 @@ pub fn part2() {
 
     println!("Found {} passwords", input_file.len());
 
-    let count = input_file
-        .into_iter()
-        .filter(|s| {
-            PasswordWithPolicy::from(s)
-                .expect("Invalid Password")
-                .is_valid_toboggan_corporate()
-        })
-        .count();
+    for unit in [
+        PositionUnit::Char,
+        PositionUnit::Byte,
+        PositionUnit::Grapheme,
+    ] {
+        let count = input_file
+            .iter()
+            .filter(|s| {
+                PasswordWithPolicy::from_with_unit(s, unit)
+                    .expect("Invalid Password")
+                    .is_valid_toboggan_corporate()
+            })
+            .count();
 
-    println!("{} are valid", count);
+        println!("{} are valid using {:?} positions", count, unit);
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use crate::pattern_cache;
+    use regex::Regex;
+    use std::time::Instant;
+
+    const PASSWORD_PATTERN: &str = "(\\d+)-(\\d+) (.): (.*)";
+
+    fn from_regex<'a>(input_regex: &Regex, input: &'a str) -> Option<PasswordWithPolicy<'a>> {
+        let groups = input_regex.captures(input)?;
+        Some(PasswordWithPolicy {
+            password: groups.get(4)?.as_str(),
+            check_character: groups[3].chars().next()?,
+            lower: groups[1].parse::<usize>().ok()?,
+            upper: groups[2].parse::<usize>().ok()?,
+            unit: PositionUnit::Char,
+        })
+    }
+
+    fn generate_input(count: usize) -> Vec<String> {
+        let mut seed: u64 = 2020;
+        let mut next = move |max: u64| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) % max
+        };
+        (0..count)
+            .map(|_| {
+                let lower = next(10) + 1;
+                let upper = lower + next(10);
+                let check_character = (b'a' + next(26) as u8) as char;
+                let password = (0..next(15) + 5)
+                    .map(|_| (b'a' + next(26) as u8) as char)
+                    .collect::<String>();
+                format!("{}-{} {}: {}", lower, upper, check_character, password)
+            })
+            .collect()
+    }
+
+    fn count_valid<'a, F>(input: &'a [String], parse: F) -> usize
+    where
+        F: Fn(&'a str) -> Option<PasswordWithPolicy<'a>>,
+    {
+        input
+            .iter()
+            .filter(|l| parse(l).expect("Invalid Password").is_valid_sled_rental())
+            .count()
+    }
+
+    #[test]
+    fn test_parse_policy() {
+        let policy = PasswordWithPolicy::from("12-345 ä: pässwörd").unwrap();
+        assert_eq!(12, policy.lower);
+        assert_eq!(345, policy.upper);
+        assert_eq!('ä', policy.check_character);
+        assert_eq!("pässwörd", policy.password);
+
+        let policy = PasswordWithPolicy::from("1-3 a: ").unwrap();
+        assert_eq!("", policy.password);
+
+        assert!(PasswordWithPolicy::from("").is_err());
+        assert!(PasswordWithPolicy::from("1-3 a abcde").is_err());
+        assert!(PasswordWithPolicy::from("1 3 a: abcde").is_err());
+        assert!(PasswordWithPolicy::from("1-x a: abcde").is_err());
+        assert!(PasswordWithPolicy::from("+1-3 a: abcde").is_err());
+        assert!(PasswordWithPolicy::from("1-3 : abcde").is_err());
+    }
+
+    #[test]
+    fn test_invalid_ranges() {
+        assert_eq!(
+            Some(PolicyError::ZeroPosition),
+            PasswordWithPolicy::from("0-3 a: abcde").err()
+        );
+        assert_eq!(
+            Some(PolicyError::ZeroPosition),
+            PasswordWithPolicy::from("0-0 a: abcde").err()
+        );
+        assert_eq!(
+            Some(PolicyError::ReversedRange(3, 1)),
+            PasswordWithPolicy::from("3-1 a: abcde").err()
+        );
+        assert_eq!(
+            Some(PolicyError::UnsupportedCheckCharacter(
+                'ä',
+                PositionUnit::Byte
+            )),
+            PasswordWithPolicy::from_with_unit("1-3 ä: äbcde", PositionUnit::Byte).err()
+        );
+    }
+
+    #[test]
+    fn test_position_units() {
+        //"é" is one char but two bytes, "e\u{301}" is one grapheme but two chars
+        let input = "1-2 b: ébx";
+        let chars = PasswordWithPolicy::from_with_unit(input, PositionUnit::Char).unwrap();
+        let bytes = PasswordWithPolicy::from_with_unit(input, PositionUnit::Byte).unwrap();
+        assert!(chars.is_valid_toboggan_corporate());
+        assert!(!bytes.is_valid_toboggan_corporate());
+
+        let input = "1-2 b: e\u{301}b";
+        let chars = PasswordWithPolicy::from_with_unit(input, PositionUnit::Char).unwrap();
+        let graphemes = PasswordWithPolicy::from_with_unit(input, PositionUnit::Grapheme).unwrap();
+        assert!(!chars.is_valid_toboggan_corporate());
+        assert!(graphemes.is_valid_toboggan_corporate());
+
+        let input = "1-4 e: e\u{301}xee";
+        let chars = PasswordWithPolicy::from_with_unit(input, PositionUnit::Char).unwrap();
+        let graphemes = PasswordWithPolicy::from_with_unit(input, PositionUnit::Grapheme).unwrap();
+        assert!(!chars.is_valid_toboggan_corporate());
+        assert!(graphemes.is_valid_toboggan_corporate());
+
+        let input = "1-1 e: e\u{301}xe";
+        let chars = PasswordWithPolicy::from_with_unit(input, PositionUnit::Char).unwrap();
+        let graphemes = PasswordWithPolicy::from_with_unit(input, PositionUnit::Grapheme).unwrap();
+        assert!(!chars.is_valid_sled_rental());
+        assert!(graphemes.is_valid_sled_rental());
+
+        let input = "5-9 a: abc";
+        let bytes = PasswordWithPolicy::from_with_unit(input, PositionUnit::Byte).unwrap();
+        assert!(!bytes.is_valid_toboggan_corporate());
+    }
+
+    #[test]
+    fn test_policy_unit_suffix() {
+        let bytes = PasswordWithPolicy::from("1-2/byte b: ébx").unwrap();
+        assert_eq!(PositionUnit::Byte, bytes.unit);
+        assert!(!bytes.is_valid_toboggan_corporate());
+
+        let graphemes = PasswordWithPolicy::from("1-2/grapheme b: e\u{301}b").unwrap();
+        assert_eq!(PositionUnit::Grapheme, graphemes.unit);
+        assert!(graphemes.is_valid_toboggan_corporate());
+
+        //The suffix wins over the default unit
+        let chars = PasswordWithPolicy::from_with_unit("1-3/char a: abc", PositionUnit::Byte);
+        assert_eq!(PositionUnit::Char, chars.unwrap().unit);
+
+        assert_eq!(
+            Some(PolicyError::UnknownUnit("word".to_string())),
+            PasswordWithPolicy::from("1-3/word a: abcde").err()
+        );
+        assert_eq!(
+            Some(PolicyError::UnsupportedCheckCharacter(
+                'ä',
+                PositionUnit::Byte
+            )),
+            PasswordWithPolicy::from("1-3/byte ä: äbcde").err()
+        );
+        assert!(PasswordWithPolicy::from("1-3/ a: abcde").is_err());
+    }
+
+    #[test]
+    fn test_parser_matches_regex() {
+        let input = generate_input(1_000);
+        let input_regex = pattern_cache::get(PASSWORD_PATTERN).expect("Invalid regular expression");
+        for line in input.iter() {
+            let expected = from_regex(&input_regex, line).unwrap();
+            let parsed = PasswordWithPolicy::from(line).unwrap();
+            assert_eq!(expected.password, parsed.password);
+            assert_eq!(expected.check_character, parsed.check_character);
+            assert_eq!(expected.lower, parsed.lower);
+            assert_eq!(expected.upper, parsed.upper);
+        }
+    }
+
+    //Run with: cargo test --release -- --ignored --nocapture bench_parse_million_passwords
+    //The timings are only printed for comparison, they depend too much on the machine to assert
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_parse_million_passwords() {
+        let input = generate_input(1_000_000);
+
+        //Compiling the regex for every line takes minutes, so it is measured on a sample only
+        let sample = &input[..10_000];
+        let start = Instant::now();
+        let regex_per_line = count_valid(sample, |l| {
+            let input_regex = Regex::new(PASSWORD_PATTERN).expect("Invalid regular expression");
+            from_regex(&input_regex, l)
+        });
+        let regex_per_line_time = start.elapsed() * (input.len() / sample.len()) as u32;
+
+        let start = Instant::now();
+        let input_regex = pattern_cache::get(PASSWORD_PATTERN).expect("Invalid regular expression");
+        let cached_regex = count_valid(&input, |l| from_regex(&input_regex, l));
+        let cached_regex_time = start.elapsed();
+
+        let start = Instant::now();
+        let hand_written = count_valid(&input, |l| PasswordWithPolicy::from(l).ok());
+        let hand_written_time = start.elapsed();
+
+        println!("Regex per line: {:?} (extrapolated)", regex_per_line_time);
+        println!("Cached regex:   {:?}", cached_regex_time);
+        println!("Hand written:   {:?}", hand_written_time);
+
+        assert_eq!(
+            regex_per_line,
+            count_valid(sample, |l| from_regex(&input_regex, l))
+        );
+        assert_eq!(cached_regex, hand_written);
+    }
 
     #[test]
     fn test_day1_part1() {
-        assert!(PasswordWithPolicy::from(&"1-3 a: abcde".to_string())
+        assert!(PasswordWithPolicy::from("1-3 a: abcde")
             .unwrap()
             .is_valid_sled_rental());
-        assert!(!PasswordWithPolicy::from(&"1-3 b: cdefg".to_string())
+        assert!(!PasswordWithPolicy::from("1-3 b: cdefg")
             .unwrap()
             .is_valid_sled_rental());
-        assert!(PasswordWithPolicy::from(&"2-9 c: ccccccccc".to_string())
+        assert!(PasswordWithPolicy::from("2-9 c: ccccccccc")
             .unwrap()
             .is_valid_sled_rental());
     }
 
     #[test]
     fn test_day1_part2() {
-        assert!(PasswordWithPolicy::from(&"1-3 a: abcde".to_string())
+        assert!(PasswordWithPolicy::from("1-3 a: abcde")
             .unwrap()
             .is_valid_toboggan_corporate());
-        assert!(!PasswordWithPolicy::from(&"1-3 b: cdefg".to_string())
+        assert!(!PasswordWithPolicy::from("1-3 b: cdefg")
             .unwrap()
             .is_valid_toboggan_corporate());
-        assert!(!PasswordWithPolicy::from(&"2-9 c: ccccccccc".to_string())
+        assert!(!PasswordWithPolicy::from("2-9 c: ccccccccc")
             .unwrap()
             .is_valid_toboggan_corporate());
     }
@@ -1,61 +1,387 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    fmt,
+    ops::{Range, RangeInclusive},
+};
 
+use crate::coordination::{IPoint, UPoint};
+use crate::export;
 use crate::load_input::load_strings;
-use crate::coordination::UPoint;
 
-enum MapElement {
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MapElement {
     OpenSquare,
     Tree,
-    Invalid,
 }
 
-struct Map {
-    max_x: usize,
-    max_y: usize,
-    coordinates: HashMap<UPoint, MapElement>,
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MapLegend {
+    pub open_square: char,
+    pub tree: char,
+}
+
+impl Default for MapLegend {
+    fn default() -> MapLegend {
+        MapLegend {
+            open_square: '.',
+            tree: '#',
+        }
+    }
+}
+
+//Rows and columns are 1-based like the lines of the input file
+#[derive(Debug, PartialEq)]
+pub enum MapError {
+    Empty,
+    AmbiguousLegend(char),
+    UnknownSymbol {
+        row: usize,
+        column: usize,
+        symbol: char,
+    },
+    RaggedRow {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MapError::Empty => write!(f, "The map is empty"),
+            MapError::AmbiguousLegend(symbol) => {
+                write!(f, "The legend uses {} for open squares and trees", symbol)
+            }
+            MapError::UnknownSymbol {
+                row,
+                column,
+                symbol,
+            } => write!(
+                f,
+                "Unknown symbol {} in row {} column {}",
+                symbol, row, column
+            ),
+            MapError::RaggedRow {
+                row,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Row {} has {} columns but the map is {} columns wide",
+                row, found, expected
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Slope {
+    //Moves right and down on every step, the rows in between are skipped
+    Step { right: i32, down: usize },
+    //Visits every row and moves right/down columns per row, rounded down
+    Fraction { right: i32, down: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RouteStep {
+    //The x coordinate is not wrapped, so it grows beyond the width of the map or gets negative
+    pub position: IPoint,
+    pub element: MapElement,
+}
+
+pub struct Route {
+    pub start: UPoint,
+    pub steps: Vec<RouteStep>,
+}
+
+impl Route {
+    pub fn tree_count(&self) -> usize {
+        self.steps
+            .iter()
+            .filter(|s| s.element == MapElement::Tree)
+            .count()
+    }
+}
+
+const SVG_CELL_SIZE: i64 = 10;
+const ROUTE_SVG_FILE: &str = "./route.svg";
+
+#[derive(Debug, PartialEq)]
+pub struct SlopeSearch {
+    pub fewest_trees: usize,
+    pub fewest: Vec<Slope>,
+    pub most_trees: usize,
+    pub most: Vec<Slope>,
+}
+
+pub struct Map {
+    width: usize,
+    rows: Vec<Vec<MapElement>>,
+    legend: MapLegend,
 }
 
 impl Map {
-    fn from<T>(input: Vec<T>) -> Map
+    pub fn from<T>(input: Vec<T>) -> Result<Map, MapError>
+    where
+        T: Into<String>,
+    {
+        Map::from_with_legend(input, MapLegend::default())
+    }
+
+    pub fn from_with_legend<T>(input: Vec<T>, legend: MapLegend) -> Result<Map, MapError>
     where
         T: Into<String>,
     {
-        let mut coordinates = HashMap::new();
-        let mut x = 0;
-        let mut y = 0;
-        for line in input {
-            let elements = line_to_elements(line.into());
-            x = 0;
-            for element in elements {
-                coordinates.insert(UPoint { x, y }, element);
-                x += 1;
+        if legend.open_square == legend.tree {
+            return Err(MapError::AmbiguousLegend(legend.tree));
+        }
+
+        let mut rows: Vec<Vec<MapElement>> = vec![];
+        for (index, line) in input.into_iter().enumerate() {
+            let elements = line_to_elements(&line.into(), &legend, index + 1)?;
+            if let Some(first) = rows.first() {
+                if first.len() != elements.len() {
+                    return Err(MapError::RaggedRow {
+                        row: index + 1,
+                        expected: first.len(),
+                        found: elements.len(),
+                    });
+                }
+            }
+            rows.push(elements);
+        }
+
+        let width = rows.first().map(|r| r.len()).unwrap_or(0);
+        if width == 0 {
+            return Err(MapError::Empty);
+        }
+        Ok(Map {
+            width,
+            rows,
+            legend,
+        })
+    }
+
+    pub fn count_trees_on_route(&self, right: usize, down: usize) -> usize {
+        let slope = Slope::Step {
+            right: right as i32,
+            down,
+        };
+        self.count_trees(UPoint { x: 0, y: 0 }, slope)
+            .expect("Invalid slope")
+    }
+
+    pub fn count_trees(&self, start: UPoint, slope: Slope) -> Result<usize, String> {
+        Ok(self
+            .route_positions(start, slope)?
+            .map_while(|(x, y)| self.element_at(x, y))
+            .filter(|element| *element == MapElement::Tree)
+            .count())
+    }
+
+    //The start itself is not part of the route steps
+    pub fn trace_route(&self, start: UPoint, slope: Slope) -> Result<Route, String> {
+        let steps = self
+            .route_positions(start, slope)?
+            .map_while(|(x, y)| {
+                Some(RouteStep {
+                    position: IPoint::from(x as i32, y as i32),
+                    element: self.element_at(x, y)?,
+                })
+            })
+            .collect();
+        Ok(Route { start, steps })
+    }
+
+    pub fn tree_product(&self, slopes: &[Slope]) -> Result<u64, String> {
+        let mut product: u64 = 1;
+        for slope in slopes {
+            let trees = self.count_trees(UPoint { x: 0, y: 0 }, *slope)? as u64;
+            product = match product.checked_mul(trees) {
+                Some(p) => p,
+                None => return Err(format!("The tree product overflows at slope {:?}", slope)),
+            };
+        }
+        Ok(product)
+    }
+
+    //Checks every step slope starting in the top left corner
+    pub fn search_slopes(
+        &self,
+        rights: RangeInclusive<i32>,
+        downs: RangeInclusive<usize>,
+    ) -> Result<SlopeSearch, String> {
+        let mut search: Option<SlopeSearch> = None;
+        for down in downs {
+            for right in rights.clone() {
+                let slope = Slope::Step { right, down };
+                let trees = self.count_trees(UPoint { x: 0, y: 0 }, slope)?;
+                let current = search.get_or_insert(SlopeSearch {
+                    fewest_trees: trees,
+                    fewest: vec![],
+                    most_trees: trees,
+                    most: vec![],
+                });
+                if trees < current.fewest_trees {
+                    current.fewest_trees = trees;
+                    current.fewest.clear();
+                }
+                if trees == current.fewest_trees {
+                    current.fewest.push(slope);
+                }
+                if trees > current.most_trees {
+                    current.most_trees = trees;
+                    current.most.clear();
+                }
+                if trees == current.most_trees {
+                    current.most.push(slope);
+                }
             }
-            y += 1;
         }
-        Map {
-            coordinates,
-            max_x: x - 1,
-            max_y: y - 1,
+        search.ok_or_else(|| "No slopes within the given bounds".to_string())
+    }
+
+    //Tiles the map as often as needed to show the whole route, hits are drawn as O and X
+    pub fn render_route(&self, route: &Route) -> String {
+        let columns = self.route_columns(route);
+        let hits = route_hits(route);
+        let mut output = String::new();
+        for y in 0..self.rows.len() {
+            for x in columns.clone() {
+                let symbol = match hits.get(&IPoint::from(x as i32, y as i32)) {
+                    Some(MapElement::Tree) => 'X',
+                    Some(_) => 'O',
+                    None => match self.element_at(x, y) {
+                        Some(MapElement::Tree) => self.legend.tree,
+                        _ => self.legend.open_square,
+                    },
+                };
+                output.push(symbol);
+            }
+            output.push('\n');
         }
+        output
     }
 
-    fn count_trees_on_route(&self, right: usize, down: usize) -> usize {
-        let mut current_pos = UPoint { x: 0, y: 0 };
-        let mut tree_count = 0;
-        while current_pos.y <= self.max_y {
-            current_pos = current_pos.x_capped_transform(right, down, self.max_x);
-            match self.coordinates.get(&current_pos) {
-                Some(MapElement::Tree) => tree_count += 1,
-                _ => continue,
+    pub fn render_route_svg(&self, route: &Route) -> String {
+        let columns = self.route_columns(route);
+        let width = (columns.end - columns.start) * SVG_CELL_SIZE;
+        let height = self.rows.len() as i64 * SVG_CELL_SIZE;
+        let to_svg_x = |x: i64| (x - columns.start) * SVG_CELL_SIZE;
+        let center = SVG_CELL_SIZE / 2;
+
+        let mut svg = format!(
+            concat!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" ",
+                "width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">\n"
+            ),
+            width, height
+        );
+        svg.push_str(&format!(
+            "  <rect width=\"{}\" height=\"{}\" fill=\"white\"/>\n",
+            width, height
+        ));
+        for y in 0..self.rows.len() {
+            for x in columns.clone() {
+                if self.element_at(x, y) != Some(MapElement::Tree) {
+                    continue;
+                }
+                svg.push_str(&format!(
+                    concat!(
+                        "  <rect x=\"{}\" y=\"{}\" ",
+                        "width=\"{2}\" height=\"{2}\" fill=\"forestgreen\"/>\n"
+                    ),
+                    to_svg_x(x),
+                    y as i64 * SVG_CELL_SIZE,
+                    SVG_CELL_SIZE
+                ));
             }
         }
-        tree_count
+        for tile in columns.clone().step_by(self.width).skip(1) {
+            svg.push_str(&format!(
+                "  <line x1=\"{0}\" y1=\"0\" x2=\"{0}\" y2=\"{1}\" stroke=\"lightgray\"/>\n",
+                to_svg_x(tile),
+                height
+            ));
+        }
+
+        let points = std::iter::once((route.start.x as i64, route.start.y as i64))
+            .chain(
+                route
+                    .steps
+                    .iter()
+                    .map(|s| (s.position.x as i64, s.position.y as i64)),
+            )
+            .map(|(x, y)| format!("{},{}", to_svg_x(x) + center, y * SVG_CELL_SIZE + center))
+            .collect::<Vec<_>>();
+        svg.push_str(&format!(
+            "  <polyline points=\"{}\" fill=\"none\" stroke=\"steelblue\"/>\n",
+            points.join(" ")
+        ));
+        for step in route.steps.iter() {
+            let fill = match step.element {
+                MapElement::Tree => "red",
+                _ => "steelblue",
+            };
+            svg.push_str(&format!(
+                "  <circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"/>\n",
+                to_svg_x(step.position.x as i64) + center,
+                step.position.y as i64 * SVG_CELL_SIZE + center,
+                center - 1,
+                fill
+            ));
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    //The unwrapped columns of all map tiles the route passes through
+    fn route_columns(&self, route: &Route) -> Range<i64> {
+        let width = self.width as i64;
+        let xs = route
+            .steps
+            .iter()
+            .map(|s| s.position.x as i64)
+            .chain(std::iter::once(route.start.x as i64));
+        let first_tile = xs.clone().min().unwrap_or(0).div_euclid(width);
+        let last_tile = xs.max().unwrap_or(0).div_euclid(width);
+        first_tile * width..(last_tile + 1) * width
+    }
+
+    //Yields the unwrapped x and the row of every step after the start
+    fn route_positions(
+        &self,
+        start: UPoint,
+        slope: Slope,
+    ) -> Result<impl Iterator<Item = (i64, usize)>, String> {
+        let (right, down, row_step) = match slope {
+            Slope::Step { right, down } => (right, down, down),
+            Slope::Fraction { right, down } => (right, down, 1),
+        };
+        if down == 0 {
+            return Err(format!("The slope {:?} does not move down", slope));
+        }
+
+        Ok((start.y + row_step..self.rows.len())
+            .step_by(row_step)
+            .map(move |y| {
+                let rows_moved = (y - start.y) as i64;
+                let x = start.x as i64 + (rows_moved * right as i64).div_euclid(down as i64);
+                (x, y)
+            }))
+    }
+
+    //The map repeats endlessly to the left and right, None below the last row
+    fn element_at(&self, x: i64, y: usize) -> Option<MapElement> {
+        let x = x.rem_euclid(self.width as i64) as usize;
+        self.rows.get(y).map(|row| row[x])
     }
 }
 
 pub fn part1() {
     let input = load_strings("./resources/Day3Input.txt").expect("Failed to read input");
-    let map = Map::from(input);
+    let map = Map::from(input).unwrap_or_else(|e| panic!("Failed to parse the map: {}", e));
     println!(
         "Found {} trees on the given route",
         map.count_trees_on_route(3, 1)
@@ -64,30 +390,68 @@ pub fn part1() {
 
 pub fn part2() {
     let input = load_strings("./resources/Day3Input.txt").expect("Failed to read input");
-    let map = Map::from(input);
-    let test_routes = vec![(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)];
-    let mut multiplied_tree_count: i64 = 1;
-    for route in test_routes {
-        multiplied_tree_count *= map.count_trees_on_route(route.0, route.1) as i64;
-    }
+    let map = Map::from(input).unwrap_or_else(|e| panic!("Failed to parse the map: {}", e));
+    let test_routes = [(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)]
+        .iter()
+        .map(|(right, down)| Slope::Step {
+            right: *right,
+            down: *down,
+        })
+        .collect::<Vec<_>>();
+    let multiplied_tree_count = map
+        .tree_product(&test_routes)
+        .expect("Failed to multiply the tree counts");
     println!(
         "The result of multipling the tree count for all routes is {}",
         multiplied_tree_count
     );
 }
 
-fn line_to_elements<T>(line: T) -> Vec<MapElement>
-where
-    T: Into<String>,
-{
-    line.into()
-        .chars()
-        .map(|c| match c {
-            '#' => MapElement::Tree,
-            '.' => MapElement::OpenSquare,
-            _ => MapElement::Invalid,
+//Prints the route of the slope over the map and writes the same as SVG
+pub fn draw_route(right: i32, down: usize) {
+    let input = load_strings("./resources/Day3Input.txt").expect("Failed to read input");
+    let map = Map::from(input).unwrap_or_else(|e| panic!("Failed to parse the map: {}", e));
+    let route = match map.trace_route(UPoint { x: 0, y: 0 }, Slope::Step { right, down }) {
+        Ok(route) => route,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    print!("{}", map.render_route(&route));
+    println!("Found {} trees on the route", route.tree_count());
+    export::write_file(ROUTE_SVG_FILE, Ok(map.render_route_svg(&route)));
+}
+
+fn route_hits(route: &Route) -> HashMap<IPoint, MapElement> {
+    route
+        .steps
+        .iter()
+        .map(|s| (s.position, s.element))
+        .collect()
+}
+
+fn line_to_elements(
+    line: &str,
+    legend: &MapLegend,
+    row: usize,
+) -> Result<Vec<MapElement>, MapError> {
+    line.chars()
+        .enumerate()
+        .map(|(index, c)| {
+            if c == legend.tree {
+                Ok(MapElement::Tree)
+            } else if c == legend.open_square {
+                Ok(MapElement::OpenSquare)
+            } else {
+                Err(MapError::UnknownSymbol {
+                    row,
+                    column: index + 1,
+                    symbol: c,
+                })
+            }
         })
-        .collect::<Vec<MapElement>>()
+        .collect()
 }
 
 #[cfg(test)]
@@ -114,11 +478,264 @@ pub mod tests {
     #[test]
     fn test_tree_count() {
         let input = get_input();
-        let map = Map::from(input);
+        let map = Map::from(input).unwrap();
         assert_eq!(2, map.count_trees_on_route(1, 1));
         assert_eq!(7, map.count_trees_on_route(3, 1));
         assert_eq!(3, map.count_trees_on_route(5, 1));
         assert_eq!(4, map.count_trees_on_route(7, 1));
         assert_eq!(2, map.count_trees_on_route(3, 2));
     }
+
+    #[test]
+    fn test_map_errors() {
+        assert_eq!(Some(MapError::Empty), Map::from(Vec::<String>::new()).err());
+        assert_eq!(Some(MapError::Empty), Map::from(vec![""]).err());
+        assert_eq!(
+            Some(MapError::UnknownSymbol {
+                row: 2,
+                column: 3,
+                symbol: 'O'
+            }),
+            Map::from(vec!["..#", "#.O", "..x"]).err()
+        );
+        assert_eq!(
+            Some(MapError::RaggedRow {
+                row: 3,
+                expected: 3,
+                found: 4
+            }),
+            Map::from(vec!["..#", "#..", "..#.", ".."]).err()
+        );
+        assert_eq!(
+            Some(MapError::RaggedRow {
+                row: 2,
+                expected: 3,
+                found: 2
+            }),
+            Map::from(vec!["..#", "#."]).err()
+        );
+    }
+
+    #[test]
+    fn test_map_legend() {
+        let legend = MapLegend {
+            open_square: ' ',
+            tree: 'T',
+        };
+        let input = get_input()
+            .into_iter()
+            .map(|l| l.replace('.', " ").replace('#', "T"))
+            .collect::<Vec<_>>();
+        let map = Map::from_with_legend(input, legend).unwrap();
+        assert_eq!(7, map.count_trees_on_route(3, 1));
+        let route = map
+            .trace_route(UPoint { x: 0, y: 0 }, Slope::Step { right: 1, down: 1 })
+            .unwrap();
+        assert!(map
+            .render_route(&route)
+            .starts_with("  TT       \nTO  T   T  \n"));
+
+        assert!(Map::from(get_input()).is_ok());
+        assert!(Map::from_with_legend(get_input(), legend).is_err());
+        assert_eq!(
+            Some(MapError::AmbiguousLegend('#')),
+            Map::from_with_legend(
+                get_input(),
+                MapLegend {
+                    open_square: '#',
+                    tree: '#'
+                }
+            )
+            .err()
+        );
+    }
+
+    #[test]
+    fn test_trace_route() {
+        let map = Map::from(get_input()).unwrap();
+        let route = map
+            .trace_route(UPoint { x: 0, y: 0 }, Slope::Step { right: 3, down: 1 })
+            .unwrap();
+        assert_eq!(10, route.steps.len());
+        assert_eq!(IPoint::from(3, 1), route.steps[0].position);
+        assert_eq!(MapElement::OpenSquare, route.steps[0].element);
+        assert_eq!(IPoint::from(30, 10), route.steps[9].position);
+        assert_eq!(MapElement::Tree, route.steps[9].element);
+        assert_eq!(7, route.tree_count());
+
+        let route = map
+            .trace_route(UPoint { x: 0, y: 0 }, Slope::Step { right: 1, down: 2 })
+            .unwrap();
+        assert_eq!(5, route.steps.len());
+        assert_eq!(IPoint::from(5, 10), route.steps[4].position);
+
+        assert!(map
+            .trace_route(UPoint { x: 0, y: 0 }, Slope::Step { right: 1, down: 0 })
+            .is_err());
+    }
+
+    #[test]
+    fn test_route_ends_at_last_row() {
+        let map = Map::from(get_input()).unwrap();
+        assert_eq!(Some(MapElement::Tree), map.element_at(-11, 1));
+        assert_eq!(Some(MapElement::Tree), map.element_at(10, 10));
+        assert_eq!(None, map.element_at(0, 11));
+
+        for start_row in [10, 11, 25] {
+            let start = UPoint { x: 0, y: start_row };
+            let route = map.trace_route(start, Slope::Step { right: 3, down: 1 });
+            assert!(route.unwrap().steps.is_empty());
+            assert_eq!(
+                Ok(0),
+                map.count_trees(start, Slope::Step { right: 3, down: 1 })
+            );
+        }
+    }
+
+    #[test]
+    fn test_trace_route_negative_and_fraction() {
+        let map = Map::from(get_input()).unwrap();
+
+        let route = map
+            .trace_route(UPoint { x: 10, y: 0 }, Slope::Step { right: -1, down: 1 })
+            .unwrap();
+        assert_eq!(IPoint::from(9, 1), route.steps[0].position);
+        assert_eq!(IPoint::from(0, 10), route.steps[9].position);
+        let route = map
+            .trace_route(UPoint { x: 0, y: 0 }, Slope::Step { right: -2, down: 1 })
+            .unwrap();
+        assert_eq!(IPoint::from(-2, 1), route.steps[0].position);
+        assert_eq!(MapElement::OpenSquare, route.steps[0].element);
+        assert_eq!(IPoint::from(-12, 6), route.steps[5].position);
+        assert_eq!(MapElement::Tree, route.steps[5].element);
+        assert_eq!(IPoint::from(-20, 10), route.steps[9].position);
+        assert_eq!(MapElement::OpenSquare, route.steps[9].element);
+        assert_eq!(2, route.tree_count());
+
+        let route = map
+            .trace_route(UPoint { x: 1, y: 2 }, Slope::Fraction { right: 1, down: 3 })
+            .unwrap();
+        let positions = route.steps.iter().map(|s| s.position).collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                IPoint::from(1, 3),
+                IPoint::from(1, 4),
+                IPoint::from(2, 5),
+                IPoint::from(2, 6),
+                IPoint::from(2, 7),
+                IPoint::from(3, 8),
+                IPoint::from(3, 9),
+                IPoint::from(3, 10),
+            ],
+            positions
+        );
+
+        let route = map
+            .trace_route(
+                UPoint { x: 0, y: 0 },
+                Slope::Fraction { right: -1, down: 2 },
+            )
+            .unwrap();
+        assert_eq!(IPoint::from(-1, 1), route.steps[0].position);
+        assert_eq!(IPoint::from(-1, 2), route.steps[1].position);
+        assert_eq!(IPoint::from(-2, 3), route.steps[2].position);
+    }
+
+    #[test]
+    fn test_tree_product() {
+        let map = Map::from(get_input()).unwrap();
+        let slopes = [(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)]
+            .iter()
+            .map(|(right, down)| Slope::Step {
+                right: *right,
+                down: *down,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(Ok(336), map.tree_product(&slopes));
+        assert_eq!(Ok(1), map.tree_product(&[]));
+        assert!(map
+            .tree_product(&[Slope::Fraction { right: 1, down: 0 }])
+            .is_err());
+    }
+
+    #[test]
+    fn test_search_slopes() {
+        let map = Map::from(get_input()).unwrap();
+        let search = map.search_slopes(1..=7, 1..=1).unwrap();
+        assert_eq!(1, search.fewest_trees);
+        assert_eq!(vec![Slope::Step { right: 2, down: 1 }], search.fewest);
+        assert_eq!(7, search.most_trees);
+        assert_eq!(vec![Slope::Step { right: 3, down: 1 }], search.most);
+
+        let search = map.search_slopes(-11..=11, 1..=3).unwrap();
+        for slope in search.fewest.iter() {
+            assert_eq!(
+                Ok(search.fewest_trees),
+                map.count_trees(UPoint { x: 0, y: 0 }, *slope)
+            );
+        }
+        assert_eq!(0, search.fewest_trees);
+        assert!(search.fewest.contains(&Slope::Step {
+            right: -10,
+            down: 3
+        }));
+        assert!(search.fewest.contains(&Slope::Step { right: 5, down: 2 }));
+
+        assert!(map.search_slopes(1..=7, 0..=1).is_err());
+    }
+
+    #[test]
+    fn test_render_route() {
+        let map = Map::from(get_input()).unwrap();
+        let route = map
+            .trace_route(UPoint { x: 0, y: 0 }, Slope::Step { right: 3, down: 1 })
+            .unwrap();
+        let expected = [
+            "..##.........##.........##.......",
+            "#..O#...#..#...#...#..#...#...#..",
+            ".#....X..#..#....#..#..#....#..#.",
+            "..#.#...#O#..#.#...#.#..#.#...#.#",
+            ".#...##..#..X...##..#..#...##..#.",
+            "..#.##.......#.X#.......#.##.....",
+            ".#.#.#....#.#.#.#.O..#.#.#.#....#",
+            ".#........#.#........X.#........#",
+            "#.##...#...#.##...#...#.X#...#...",
+            "#...##....##...##....##...#X....#",
+            ".#..#...#.#.#..#...#.#.#..#...X.#",
+        ];
+        assert_eq!(
+            format!("{}\n", expected.join("\n")),
+            map.render_route(&route)
+        );
+
+        let route = map
+            .trace_route(UPoint { x: 0, y: 8 }, Slope::Step { right: -1, down: 1 })
+            .unwrap();
+        let rendered = map.render_route(&route);
+        let last_rows = rendered.lines().skip(8).collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                "#.##...#...#.##...#...",
+                "#...##....X#...##....#",
+                ".#..#...#O#.#..#...#.#",
+            ],
+            last_rows
+        );
+    }
+
+    #[test]
+    fn test_render_route_svg() {
+        let map = Map::from(get_input()).unwrap();
+        let route = map
+            .trace_route(UPoint { x: 0, y: 0 }, Slope::Step { right: 3, down: 1 })
+            .unwrap();
+        let svg = map.render_route_svg(&route);
+        assert!(svg
+            .starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"330\" height=\"110\""));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(7, svg.matches("fill=\"red\"").count());
+        assert_eq!(3, svg.matches("<circle").count() - 7);
+        assert_eq!(2, svg.matches("<line").count());
+        assert!(svg.contains("<polyline points=\"5,5 35,15 65,25 "));
+    }
 }